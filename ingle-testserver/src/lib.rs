@@ -0,0 +1,75 @@
+//! An in-memory implementation of the Firestore gRPC API, for hermetic tests.
+//!
+//! This supports getting, listing, creating, updating & deleting documents,
+//! commits, transactions & simple queries.  It's not a full emulator - anything
+//! it doesn't support returns `UNIMPLEMENTED`.
+//!
+//! Start a `TestServer` & point `DatabaseBuilder::http_endpoint` at its `host`,
+//! using `emulator_owner_credentials`.
+
+// tonic's Status is the error type of every RPC, so it's returned everywhere
+#![allow(clippy::result_large_err)]
+
+use std::{io, net::SocketAddr};
+
+use tokio::{net::TcpListener, sync::oneshot};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+
+// We only use a small part of the generated code
+#[allow(dead_code)]
+mod google;
+mod query;
+mod service;
+mod store;
+
+use self::{google::firestore::v1::firestore_server::FirestoreServer, service::InMemoryFirestore};
+
+/// A Firestore server running on a local port.
+///
+/// The server starts out empty & is shut down when this is dropped.
+pub struct TestServer {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl TestServer {
+    /// Starts a server on a random port on localhost.
+    ///
+    /// This must be called from within a tokio runtime.
+    pub async fn start() -> io::Result<TestServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let address = listener.local_addr()?;
+        let (shutdown, shutdown_signal) = oneshot::channel();
+
+        let server = Server::builder()
+            .add_service(FirestoreServer::new(InMemoryFirestore::default()))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                shutdown_signal.await.ok();
+            });
+
+        tokio::spawn(server);
+
+        Ok(TestServer {
+            address,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The host & port of the server, for `DatabaseBuilder::http_endpoint`.
+    pub fn host(&self) -> String {
+        self.address.to_string()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
@@ -0,0 +1,555 @@
+//! Evaluation of structured queries against stored documents.
+//!
+//! This supports the subset of queries ingle needs: a single collection,
+//! field, unary & `AND` composite filters, ordering, offsets, limits &
+//! projections.  Cursors aren't supported.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use tonic::Status;
+
+use crate::google::firestore::v1::{
+    structured_query::{
+        composite_filter, field_filter, filter::FilterType, unary_filter, CollectionSelector,
+        Direction, FieldFilter, Filter, Order, UnaryFilter,
+    },
+    value::ValueType,
+    Document, MapValue, StructuredQuery, Value,
+};
+
+/// The special field path that refers to a documents name.
+static NAME_FIELD: &str = "__name__";
+
+/// Runs `query` against `documents`, which should be ordered by name.
+pub fn run<'a>(
+    parent: &str,
+    query: &StructuredQuery,
+    documents: impl Iterator<Item = &'a Document>,
+) -> Result<Vec<Document>, Status> {
+    if query.start_at.is_some() || query.end_at.is_some() {
+        return Err(Status::unimplemented(
+            "Query cursors are not supported by the test server",
+        ));
+    }
+
+    let collection = match query.from.as_slice() {
+        [collection] => collection,
+        _ => {
+            return Err(Status::invalid_argument(
+                "Queries must select exactly one collection",
+            ))
+        }
+    };
+
+    let orders = query
+        .order_by
+        .iter()
+        .map(|order| {
+            let field = order
+                .field
+                .as_ref()
+                .ok_or_else(|| Status::invalid_argument("Order is missing a field"))?;
+
+            Ok((field.field_path.as_str(), order.direction))
+        })
+        .collect::<Result<Vec<_>, Status>>()?;
+
+    let mut results = vec![];
+    for document in documents {
+        if !in_collection(parent, collection, &document.name) {
+            continue;
+        }
+        if let Some(filter) = &query.r#where {
+            if !matches(document, filter)? {
+                continue;
+            }
+        }
+        // Documents without the fields we're ordering by are left out.
+        if orders
+            .iter()
+            .any(|(path, _)| field_value(document, path).is_none())
+        {
+            continue;
+        }
+        results.push(document);
+    }
+
+    results.sort_by(|a, b| compare_documents(a, b, &query.order_by));
+
+    let limit = match query.limit {
+        Some(limit) if limit >= 0 => limit as usize,
+        Some(_) => return Err(Status::invalid_argument("Limit must be positive")),
+        None => usize::MAX,
+    };
+
+    Ok(results
+        .into_iter()
+        .skip(query.offset.max(0) as usize)
+        .take(limit)
+        .map(|document| match &query.select {
+            Some(projection) => {
+                let paths = projection.fields.iter().map(|f| f.field_path.as_str());
+                project(document, paths)
+            }
+            None => document.clone(),
+        })
+        .collect())
+}
+
+/// Returns a copy of `document` with only the fields in `paths`.
+pub fn project<'a>(document: &Document, paths: impl Iterator<Item = &'a str>) -> Document {
+    let mut fields = HashMap::new();
+    for path in paths {
+        let segments = split_path(path);
+        if let Some(value) = lookup(&document.fields, &segments) {
+            set_field(&mut fields, &segments, Some(value.clone()));
+        }
+    }
+
+    Document {
+        fields,
+        ..document.clone()
+    }
+}
+
+fn in_collection(parent: &str, collection: &CollectionSelector, name: &str) -> bool {
+    let rest = match name
+        .strip_prefix(parent)
+        .and_then(|rest| rest.strip_prefix('/'))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    // The rest of the name alternates between collection IDs & document IDs
+    let segments = rest.split('/').collect::<Vec<_>>();
+    if collection.all_descendants {
+        segments.len() >= 2 && segments[segments.len() - 2] == collection.collection_id
+    } else {
+        segments.len() == 2 && segments[0] == collection.collection_id
+    }
+}
+
+fn matches(document: &Document, filter: &Filter) -> Result<bool, Status> {
+    match &filter.filter_type {
+        Some(FilterType::CompositeFilter(composite)) => {
+            if composite.op != composite_filter::Operator::And as i32 {
+                return Err(Status::invalid_argument(
+                    "Unknown composite filter operator",
+                ));
+            }
+            for filter in &composite.filters {
+                if !matches(document, filter)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        Some(FilterType::FieldFilter(filter)) => field_filter_matches(document, filter),
+        Some(FilterType::UnaryFilter(filter)) => unary_filter_matches(document, filter),
+        None => Err(Status::invalid_argument("Filter is missing a filter type")),
+    }
+}
+
+fn field_filter_matches(document: &Document, filter: &FieldFilter) -> Result<bool, Status> {
+    use field_filter::Operator;
+
+    let path = match &filter.field {
+        Some(field) => &field.field_path,
+        None => return Err(Status::invalid_argument("Filter is missing a field")),
+    };
+    let operand = filter
+        .value
+        .as_ref()
+        .ok_or_else(|| Status::invalid_argument("Filter is missing a value"))?;
+    let op = Operator::from_i32(filter.op)
+        .filter(|op| *op != Operator::Unspecified)
+        .ok_or_else(|| Status::invalid_argument("Unknown field filter operator"))?;
+
+    let value = match field_value(document, path) {
+        Some(value) => value,
+        None => return Ok(false),
+    };
+
+    let comparable = type_order(&value) == type_order(operand);
+    let ordering = compare(&value, operand);
+
+    Ok(match op {
+        Operator::LessThan => comparable && ordering == Ordering::Less,
+        Operator::LessThanOrEqual => comparable && ordering != Ordering::Greater,
+        Operator::GreaterThan => comparable && ordering == Ordering::Greater,
+        Operator::GreaterThanOrEqual => comparable && ordering != Ordering::Less,
+        Operator::Equal => ordering == Ordering::Equal,
+        Operator::NotEqual => !is_null(&value) && ordering != Ordering::Equal,
+        Operator::ArrayContains => array_values(&value).iter().any(|v| equal(v, operand)),
+        Operator::In => array_values(operand).iter().any(|v| equal(&value, v)),
+        Operator::ArrayContainsAny => array_values(&value)
+            .iter()
+            .any(|v| array_values(operand).iter().any(|o| equal(v, o))),
+        Operator::NotIn => {
+            !is_null(&value) && !array_values(operand).iter().any(|v| equal(&value, v))
+        }
+        Operator::Unspecified => unreachable!(),
+    })
+}
+
+fn unary_filter_matches(document: &Document, filter: &UnaryFilter) -> Result<bool, Status> {
+    use unary_filter::{OperandType, Operator};
+
+    let path = match &filter.operand_type {
+        Some(OperandType::Field(field)) => &field.field_path,
+        None => return Err(Status::invalid_argument("Filter is missing a field")),
+    };
+    let op = Operator::from_i32(filter.op)
+        .filter(|op| *op != Operator::Unspecified)
+        .ok_or_else(|| Status::invalid_argument("Unknown unary filter operator"))?;
+
+    let value = match field_value(document, path) {
+        Some(value) => value,
+        None => return Ok(false),
+    };
+    let is_nan = matches!(value.value_type, Some(ValueType::DoubleValue(d)) if d.is_nan());
+
+    Ok(match op {
+        Operator::IsNan => is_nan,
+        Operator::IsNull => is_null(&value),
+        Operator::IsNotNan => !is_nan && !is_null(&value),
+        Operator::IsNotNull => !is_null(&value),
+        Operator::Unspecified => unreachable!(),
+    })
+}
+
+fn compare_documents(a: &Document, b: &Document, orders: &[Order]) -> Ordering {
+    let mut last_direction = Direction::Ascending as i32;
+
+    for order in orders {
+        let path = order
+            .field
+            .as_ref()
+            .map(|f| f.field_path.as_str())
+            .unwrap_or_default();
+        let ordering = match (field_value(a, path), field_value(b, path)) {
+            (Some(a), Some(b)) => compare(&a, &b),
+            _ => Ordering::Equal,
+        };
+
+        last_direction = order.direction;
+        let ordering = apply_direction(ordering, order.direction);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // Ties are broken by the document name, in the direction of the last order
+    apply_direction(compare_names(&a.name, &b.name), last_direction)
+}
+
+fn apply_direction(ordering: Ordering, direction: i32) -> Ordering {
+    if direction == Direction::Descending as i32 {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+/// Looks up a field path in a document, including the special `__name__`.
+fn field_value(document: &Document, path: &str) -> Option<Value> {
+    if path == NAME_FIELD {
+        return Some(Value {
+            value_type: Some(ValueType::ReferenceValue(document.name.clone())),
+        });
+    }
+
+    lookup(&document.fields, &split_path(path)).cloned()
+}
+
+/// Looks up a field path that's already been split into segments.
+pub fn lookup<'a>(fields: &'a HashMap<String, Value>, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    let mut value = fields.get(first)?;
+    for segment in rest {
+        value = match &value.value_type {
+            Some(ValueType::MapValue(map)) => map.fields.get(segment)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// Sets (or removes, if `value` is `None`) a field path in a set of fields.
+///
+/// Any maps along the path that don't exist are created.
+pub fn set_field(fields: &mut HashMap<String, Value>, path: &[String], value: Option<Value>) {
+    match path {
+        [] => {}
+        [last] => match value {
+            Some(value) => {
+                fields.insert(last.clone(), value);
+            }
+            None => {
+                fields.remove(last);
+            }
+        },
+        [first, rest @ ..] => {
+            let entry = fields.entry(first.clone()).or_default();
+            if !matches!(entry.value_type, Some(ValueType::MapValue(_))) {
+                entry.value_type = Some(ValueType::MapValue(MapValue::default()));
+            }
+            if let Some(ValueType::MapValue(map)) = &mut entry.value_type {
+                set_field(&mut map.fields, rest, value);
+            }
+        }
+    }
+}
+
+/// Splits a field path into its segments, handling backtick quoted segments.
+pub fn split_path(path: &str) -> Vec<String> {
+    let mut segments = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    segments.push(current);
+
+    segments
+}
+
+fn is_null(value: &Value) -> bool {
+    matches!(value.value_type, None | Some(ValueType::NullValue(_)))
+}
+
+fn array_values(value: &Value) -> &[Value] {
+    match &value.value_type {
+        Some(ValueType::ArrayValue(array)) => &array.values,
+        _ => &[],
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    compare(a, b) == Ordering::Equal
+}
+
+/// The position of a values type in Firestores ordering of types.
+fn type_order(value: &Value) -> u8 {
+    match &value.value_type {
+        None | Some(ValueType::NullValue(_)) => 0,
+        Some(ValueType::BooleanValue(_)) => 1,
+        Some(ValueType::IntegerValue(_)) | Some(ValueType::DoubleValue(_)) => 2,
+        Some(ValueType::TimestampValue(_)) => 3,
+        Some(ValueType::StringValue(_)) => 4,
+        Some(ValueType::BytesValue(_)) => 5,
+        Some(ValueType::ReferenceValue(_)) => 6,
+        Some(ValueType::GeoPointValue(_)) => 7,
+        Some(ValueType::ArrayValue(_)) => 8,
+        Some(ValueType::MapValue(_)) => 9,
+    }
+}
+
+/// Compares two values the same way Firestore orders them.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    use ValueType::*;
+
+    match (&a.value_type, &b.value_type) {
+        (Some(BooleanValue(a)), Some(BooleanValue(b))) => a.cmp(b),
+        (Some(IntegerValue(a)), Some(IntegerValue(b))) => a.cmp(b),
+        (Some(IntegerValue(a)), Some(DoubleValue(b))) => compare_doubles(*a as f64, *b),
+        (Some(DoubleValue(a)), Some(IntegerValue(b))) => compare_doubles(*a, *b as f64),
+        (Some(DoubleValue(a)), Some(DoubleValue(b))) => compare_doubles(*a, *b),
+        (Some(TimestampValue(a)), Some(TimestampValue(b))) => {
+            (a.seconds, a.nanos).cmp(&(b.seconds, b.nanos))
+        }
+        (Some(StringValue(a)), Some(StringValue(b))) => a.cmp(b),
+        (Some(BytesValue(a)), Some(BytesValue(b))) => a.cmp(b),
+        (Some(ReferenceValue(a)), Some(ReferenceValue(b))) => compare_names(a, b),
+        (Some(GeoPointValue(a)), Some(GeoPointValue(b))) => compare_doubles(a.latitude, b.latitude)
+            .then_with(|| compare_doubles(a.longitude, b.longitude)),
+        (Some(ArrayValue(a)), Some(ArrayValue(b))) => a
+            .values
+            .iter()
+            .zip(&b.values)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| a.values.len().cmp(&b.values.len())),
+        (Some(MapValue(a)), Some(MapValue(b))) => {
+            let mut a = a.fields.iter().collect::<Vec<_>>();
+            let mut b = b.fields.iter().collect::<Vec<_>>();
+            a.sort_by_key(|(key, _)| *key);
+            b.sort_by_key(|(key, _)| *key);
+
+            a.iter()
+                .zip(&b)
+                .map(|((a_key, a), (b_key, b))| a_key.cmp(b_key).then_with(|| compare(a, b)))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+        _ => type_order(a).cmp(&type_order(b)),
+    }
+}
+
+/// Compares doubles, with NaN sorting before every other number.
+fn compare_doubles(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+/// Compares document names segment by segment.
+fn compare_names(a: &str, b: &str) -> Ordering {
+    a.split('/').cmp(b.split('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google::firestore::v1::structured_query::FieldReference;
+
+    static PARENT: &str = "projects/test/databases/(default)/documents";
+
+    fn integer(value: i64) -> Value {
+        Value {
+            value_type: Some(ValueType::IntegerValue(value)),
+        }
+    }
+
+    fn string(value: &str) -> Value {
+        Value {
+            value_type: Some(ValueType::StringValue(value.into())),
+        }
+    }
+
+    fn book(id: &str, title: &str, pages: i64) -> Document {
+        let mut fields = HashMap::new();
+        fields.insert("title".to_string(), string(title));
+        fields.insert("pages".to_string(), integer(pages));
+
+        Document {
+            name: format!("{}/books/{}", PARENT, id),
+            fields,
+            create_time: None,
+            update_time: None,
+        }
+    }
+
+    fn field_filter(path: &str, op: field_filter::Operator, value: Value) -> Filter {
+        Filter {
+            filter_type: Some(FilterType::FieldFilter(FieldFilter {
+                field: Some(FieldReference {
+                    field_path: path.into(),
+                }),
+                op: op as i32,
+                value: Some(value),
+            })),
+        }
+    }
+
+    fn books_query() -> StructuredQuery {
+        StructuredQuery {
+            from: vec![CollectionSelector {
+                collection_id: "books".into(),
+                all_descendants: false,
+            }],
+            ..StructuredQuery::default()
+        }
+    }
+
+    fn ids(results: Vec<Document>) -> Vec<String> {
+        results
+            .into_iter()
+            .map(|d| d.name.rsplit('/').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_filters_and_ordering() {
+        let documents = [
+            book("a", "Northern Lights", 399),
+            book("b", "The Subtle Knife", 326),
+            book("c", "The Amber Spyglass", 518),
+        ];
+
+        let query = StructuredQuery {
+            r#where: Some(field_filter(
+                "pages",
+                field_filter::Operator::GreaterThan,
+                integer(350),
+            )),
+            order_by: vec![Order {
+                field: Some(FieldReference {
+                    field_path: "pages".into(),
+                }),
+                direction: Direction::Descending as i32,
+            }],
+            ..books_query()
+        };
+
+        let results = run(PARENT, &query, documents.iter()).unwrap();
+
+        assert_eq!(ids(results), vec!["c", "a"]);
+    }
+
+    #[test]
+    fn test_limit_offset_and_collections() {
+        let mut documents = vec![
+            book("a", "Northern Lights", 399),
+            book("b", "The Subtle Knife", 326),
+            book("c", "The Amber Spyglass", 518),
+        ];
+        documents.push(Document {
+            name: format!("{}/books/a/characters/lyra", PARENT),
+            ..Document::default()
+        });
+
+        let query = StructuredQuery {
+            offset: 1,
+            limit: Some(1),
+            ..books_query()
+        };
+
+        assert_eq!(
+            ids(run(PARENT, &query, documents.iter()).unwrap()),
+            vec!["b"]
+        );
+        assert_eq!(
+            ids(run(PARENT, &books_query(), documents.iter()).unwrap()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_compare_across_types() {
+        assert_eq!(compare(&integer(1), &string("1")), Ordering::Less);
+        assert_eq!(
+            compare(
+                &integer(2),
+                &Value {
+                    value_type: Some(ValueType::DoubleValue(1.5))
+                }
+            ),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_field_paths() {
+        assert_eq!(split_path("a.b"), vec!["a", "b"]);
+        assert_eq!(split_path("`a.b`.c"), vec!["a.b", "c"]);
+
+        let mut fields = HashMap::new();
+        set_field(&mut fields, &split_path("a.b"), Some(integer(1)));
+
+        assert_eq!(lookup(&fields, &split_path("a.b")), Some(&integer(1)));
+        assert_eq!(lookup(&fields, &split_path("a.c")), None);
+    }
+}
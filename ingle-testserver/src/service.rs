@@ -0,0 +1,401 @@
+//! The Firestore gRPC service, backed by a `Store`.
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::stream;
+use tonic::{Request, Response, Status};
+
+use crate::{
+    google::firestore::v1::{
+        self as firestore, firestore_server::Firestore, get_document_request,
+        list_documents_request, run_query_request, transaction_options, write::Operation,
+    },
+    query,
+    store::Store,
+};
+
+type ResponseStream<T> = stream::Iter<std::vec::IntoIter<Result<T, Status>>>;
+
+#[derive(Clone, Default)]
+pub struct InMemoryFirestore {
+    store: Arc<Mutex<Store>>,
+}
+
+impl InMemoryFirestore {
+    /// Writes a single document outside of a transaction & returns the result.
+    fn write_document(
+        &self,
+        document: firestore::Document,
+        update_mask: Option<firestore::DocumentMask>,
+        current_document: Option<firestore::Precondition>,
+    ) -> Result<firestore::Document, Status> {
+        let name = document.name.clone();
+        let write = firestore::Write {
+            update_mask,
+            update_transforms: vec![],
+            current_document,
+            operation: Some(Operation::Update(document)),
+        };
+
+        let mut store = self.store.lock().unwrap();
+        store.commit(None, vec![write])?;
+        Ok(store.get(None, &name)?.expect("Written document to exist"))
+    }
+}
+
+#[tonic::async_trait]
+impl Firestore for InMemoryFirestore {
+    async fn get_document(
+        &self,
+        request: Request<firestore::GetDocumentRequest>,
+    ) -> Result<Response<firestore::Document>, Status> {
+        let request = request.into_inner();
+        let transaction = match &request.consistency_selector {
+            Some(get_document_request::ConsistencySelector::Transaction(id)) => Some(id.as_slice()),
+            Some(get_document_request::ConsistencySelector::ReadTime(_)) => {
+                return Err(read_time_unsupported())
+            }
+            None => None,
+        };
+
+        let document = self
+            .store
+            .lock()
+            .unwrap()
+            .get(transaction, &request.name)?
+            .ok_or_else(|| Status::not_found(format!("Document not found: {}", request.name)))?;
+
+        Ok(Response::new(match request.mask {
+            Some(mask) => query::project(&document, mask.field_paths.iter().map(String::as_str)),
+            None => document,
+        }))
+    }
+
+    async fn list_documents(
+        &self,
+        request: Request<firestore::ListDocumentsRequest>,
+    ) -> Result<Response<firestore::ListDocumentsResponse>, Status> {
+        let request = request.into_inner();
+        let transaction = match &request.consistency_selector {
+            Some(list_documents_request::ConsistencySelector::Transaction(id)) => {
+                Some(id.as_slice())
+            }
+            Some(list_documents_request::ConsistencySelector::ReadTime(_)) => {
+                return Err(read_time_unsupported())
+            }
+            None => None,
+        };
+        if !request.order_by.is_empty() && request.order_by != "__name__" {
+            return Err(Status::unimplemented(
+                "The test server can only order listed documents by name",
+            ));
+        }
+        if request.show_missing {
+            return Err(Status::unimplemented(
+                "The test server doesn't support show_missing",
+            ));
+        }
+
+        let prefix = format!("{}/{}/", request.parent, request.collection_id);
+        if !request.page_token.is_empty() && !request.page_token.starts_with(&prefix) {
+            return Err(Status::invalid_argument("Invalid page token"));
+        }
+        let page_size = match request.page_size {
+            size if size > 0 => size as usize,
+            _ => usize::MAX,
+        };
+
+        let mut store = self.store.lock().unwrap();
+        let (documents, next_page_token) = {
+            let mut matching = store
+                .documents()
+                .filter(|document| {
+                    document.name.starts_with(&prefix)
+                        // Documents in sub-collections have more path segments
+                        && !document.name[prefix.len()..].contains('/')
+                        && document.name > request.page_token
+                })
+                .cloned();
+
+            let documents = matching.by_ref().take(page_size).collect::<Vec<_>>();
+            let next_page_token = match (matching.next(), documents.last()) {
+                (Some(_), Some(last)) => last.name.clone(),
+                _ => String::new(),
+            };
+
+            (documents, next_page_token)
+        };
+
+        store.lock(transaction, documents.iter().map(|d| d.name.clone()))?;
+
+        let documents = match request.mask {
+            Some(mask) => documents
+                .iter()
+                .map(|document| {
+                    query::project(document, mask.field_paths.iter().map(String::as_str))
+                })
+                .collect(),
+            None => documents,
+        };
+
+        Ok(Response::new(firestore::ListDocumentsResponse {
+            documents,
+            next_page_token,
+        }))
+    }
+
+    async fn update_document(
+        &self,
+        request: Request<firestore::UpdateDocumentRequest>,
+    ) -> Result<Response<firestore::Document>, Status> {
+        let request = request.into_inner();
+        let document = request
+            .document
+            .ok_or_else(|| Status::invalid_argument("Missing a document to update"))?;
+
+        let updated =
+            self.write_document(document, request.update_mask, request.current_document)?;
+
+        Ok(Response::new(match request.mask {
+            Some(mask) => query::project(&updated, mask.field_paths.iter().map(String::as_str)),
+            None => updated,
+        }))
+    }
+
+    async fn delete_document(
+        &self,
+        request: Request<firestore::DeleteDocumentRequest>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+
+        let delete = firestore::Write {
+            update_mask: None,
+            update_transforms: vec![],
+            current_document: request.current_document,
+            operation: Some(Operation::Delete(request.name)),
+        };
+
+        self.store.lock().unwrap().commit(None, vec![delete])?;
+
+        Ok(Response::new(()))
+    }
+
+    type BatchGetDocumentsStream = ResponseStream<firestore::BatchGetDocumentsResponse>;
+
+    async fn batch_get_documents(
+        &self,
+        _request: Request<firestore::BatchGetDocumentsRequest>,
+    ) -> Result<Response<Self::BatchGetDocumentsStream>, Status> {
+        Err(unimplemented("BatchGetDocuments"))
+    }
+
+    async fn begin_transaction(
+        &self,
+        request: Request<firestore::BeginTransactionRequest>,
+    ) -> Result<Response<firestore::BeginTransactionResponse>, Status> {
+        let options = request.into_inner().options;
+
+        let transaction = begin_transaction(&mut self.store.lock().unwrap(), options)?;
+
+        Ok(Response::new(firestore::BeginTransactionResponse {
+            transaction,
+        }))
+    }
+
+    async fn commit(
+        &self,
+        request: Request<firestore::CommitRequest>,
+    ) -> Result<Response<firestore::CommitResponse>, Status> {
+        let request = request.into_inner();
+        let transaction = if request.transaction.is_empty() {
+            None
+        } else {
+            Some(request.transaction.as_slice())
+        };
+
+        let (write_results, commit_time) = self
+            .store
+            .lock()
+            .unwrap()
+            .commit(transaction, request.writes)?;
+
+        Ok(Response::new(firestore::CommitResponse {
+            write_results,
+            commit_time: Some(commit_time),
+        }))
+    }
+
+    async fn rollback(
+        &self,
+        request: Request<firestore::RollbackRequest>,
+    ) -> Result<Response<()>, Status> {
+        self.store
+            .lock()
+            .unwrap()
+            .rollback(&request.into_inner().transaction)?;
+
+        Ok(Response::new(()))
+    }
+
+    type RunQueryStream = ResponseStream<firestore::RunQueryResponse>;
+
+    async fn run_query(
+        &self,
+        request: Request<firestore::RunQueryRequest>,
+    ) -> Result<Response<Self::RunQueryStream>, Status> {
+        let request = request.into_inner();
+        let query = match request.query_type {
+            Some(run_query_request::QueryType::StructuredQuery(query)) => query,
+            None => return Err(Status::invalid_argument("Missing a structured query")),
+        };
+
+        let mut store = self.store.lock().unwrap();
+        let (transaction, new_transaction) = match request.consistency_selector {
+            Some(run_query_request::ConsistencySelector::Transaction(id)) => (id, false),
+            Some(run_query_request::ConsistencySelector::NewTransaction(options)) => {
+                (begin_transaction(&mut store, Some(options))?, true)
+            }
+            Some(run_query_request::ConsistencySelector::ReadTime(_)) => {
+                return Err(read_time_unsupported())
+            }
+            None => (vec![], false),
+        };
+
+        let documents = query::run(&request.parent, &query, store.documents())?;
+        if !transaction.is_empty() {
+            store.lock(Some(&transaction), documents.iter().map(|d| d.name.clone()))?;
+        }
+        let read_time = store.tick();
+
+        // A newly started transaction is returned in the first response, and
+        // Firestore always sends at least one response with a read time.
+        let mut transaction = if new_transaction { transaction } else { vec![] };
+        let mut responses = documents.into_iter().map(Some).collect::<Vec<_>>();
+        if responses.is_empty() {
+            responses.push(None);
+        }
+
+        let responses = responses
+            .into_iter()
+            .map(|document| {
+                Ok(firestore::RunQueryResponse {
+                    transaction: std::mem::take(&mut transaction),
+                    document,
+                    read_time: Some(read_time.clone()),
+                    skipped_results: 0,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(stream::iter(responses)))
+    }
+
+    async fn partition_query(
+        &self,
+        _request: Request<firestore::PartitionQueryRequest>,
+    ) -> Result<Response<firestore::PartitionQueryResponse>, Status> {
+        Err(unimplemented("PartitionQuery"))
+    }
+
+    type WriteStream = ResponseStream<firestore::WriteResponse>;
+
+    async fn write(
+        &self,
+        _request: Request<tonic::Streaming<firestore::WriteRequest>>,
+    ) -> Result<Response<Self::WriteStream>, Status> {
+        Err(unimplemented("Write"))
+    }
+
+    type ListenStream = ResponseStream<firestore::ListenResponse>;
+
+    async fn listen(
+        &self,
+        _request: Request<tonic::Streaming<firestore::ListenRequest>>,
+    ) -> Result<Response<Self::ListenStream>, Status> {
+        Err(unimplemented("Listen"))
+    }
+
+    async fn list_collection_ids(
+        &self,
+        _request: Request<firestore::ListCollectionIdsRequest>,
+    ) -> Result<Response<firestore::ListCollectionIdsResponse>, Status> {
+        Err(unimplemented("ListCollectionIds"))
+    }
+
+    async fn batch_write(
+        &self,
+        _request: Request<firestore::BatchWriteRequest>,
+    ) -> Result<Response<firestore::BatchWriteResponse>, Status> {
+        Err(unimplemented("BatchWrite"))
+    }
+
+    async fn create_document(
+        &self,
+        request: Request<firestore::CreateDocumentRequest>,
+    ) -> Result<Response<firestore::Document>, Status> {
+        let request = request.into_inner();
+        let document_id = if request.document_id.is_empty() {
+            new_doc_id()
+        } else {
+            request.document_id
+        };
+        let document = firestore::Document {
+            name: format!(
+                "{}/{}/{}",
+                request.parent, request.collection_id, document_id
+            ),
+            ..request.document.unwrap_or_default()
+        };
+
+        let must_not_exist = firestore::Precondition {
+            condition_type: Some(firestore::precondition::ConditionType::Exists(false)),
+        };
+        let created = self.write_document(document, None, Some(must_not_exist))?;
+
+        Ok(Response::new(match request.mask {
+            Some(mask) => query::project(&created, mask.field_paths.iter().map(String::as_str)),
+            None => created,
+        }))
+    }
+}
+
+fn begin_transaction(
+    store: &mut Store,
+    options: Option<firestore::TransactionOptions>,
+) -> Result<Vec<u8>, Status> {
+    match options.and_then(|options| options.mode) {
+        Some(transaction_options::Mode::ReadOnly(read_only)) => {
+            if read_only.consistency_selector.is_some() {
+                return Err(read_time_unsupported());
+            }
+            Ok(store.begin_transaction(true))
+        }
+        Some(transaction_options::Mode::ReadWrite(read_write)) => {
+            // A retry replaces the transaction it's retrying
+            if !read_write.retry_transaction.is_empty() {
+                store.rollback(&read_write.retry_transaction).ok();
+            }
+            Ok(store.begin_transaction(false))
+        }
+        None => Ok(store.begin_transaction(false)),
+    }
+}
+
+fn new_doc_id() -> String {
+    use rand::distributions::Alphanumeric;
+    use rand::{thread_rng, Rng};
+
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect()
+}
+
+fn read_time_unsupported() -> Status {
+    Status::unimplemented("Reading at a specific time is not supported by the test server")
+}
+
+fn unimplemented(method: &str) -> Status {
+    Status::unimplemented(format!("{} is not supported by the test server", method))
+}
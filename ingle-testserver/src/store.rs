@@ -0,0 +1,352 @@
+//! The in-memory state behind the test server.
+//!
+//! Transactions are modelled on Firestores pessimistic locking: reads in a
+//! read-write transaction take a lock on each document they return, and those
+//! locks are held until the transaction commits or rolls back.  Where
+//! Firestore would make a conflicting write wait for the lock, this fails it
+//! straight away with `ABORTED`, which keeps tests deterministic.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::SystemTime,
+};
+
+use prost_types::Timestamp;
+use tonic::Status;
+
+use crate::{
+    google::firestore::v1::{
+        precondition::ConditionType, write::Operation, Document, Precondition, Write, WriteResult,
+    },
+    query,
+};
+
+#[derive(Default)]
+pub struct Store {
+    documents: BTreeMap<String, Document>,
+    transactions: HashMap<Vec<u8>, Transaction>,
+    next_transaction: u64,
+    last_time: Option<Timestamp>,
+}
+
+#[derive(Default)]
+struct Transaction {
+    read_only: bool,
+    locks: HashSet<String>,
+}
+
+impl Store {
+    pub fn begin_transaction(&mut self, read_only: bool) -> Vec<u8> {
+        self.next_transaction += 1;
+        let id = self.next_transaction.to_be_bytes().to_vec();
+
+        self.transactions.insert(
+            id.clone(),
+            Transaction {
+                read_only,
+                locks: HashSet::new(),
+            },
+        );
+
+        id
+    }
+
+    pub fn rollback(&mut self, transaction: &[u8]) -> Result<(), Status> {
+        self.transactions
+            .remove(transaction)
+            .map(|_| ())
+            .ok_or_else(transaction_not_found)
+    }
+
+    /// Gets a single document, locking it if we're in a read-write transaction.
+    pub fn get(
+        &mut self,
+        transaction: Option<&[u8]>,
+        name: &str,
+    ) -> Result<Option<Document>, Status> {
+        self.lock(transaction, std::iter::once(name.to_string()))?;
+
+        Ok(self.documents.get(name).cloned())
+    }
+
+    /// Returns every document in the database, ordered by name.
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.documents.values()
+    }
+
+    /// Takes locks on `names` on behalf of a transaction.
+    ///
+    /// Reads outside of a transaction or in read-only transactions don't take
+    /// locks, but do check that the transaction exists.
+    pub fn lock(
+        &mut self,
+        transaction: Option<&[u8]>,
+        names: impl Iterator<Item = String>,
+    ) -> Result<(), Status> {
+        let transaction = match transaction {
+            Some(id) => self
+                .transactions
+                .get_mut(id)
+                .ok_or_else(transaction_not_found)?,
+            None => return Ok(()),
+        };
+
+        if !transaction.read_only {
+            transaction.locks.extend(names);
+        }
+
+        Ok(())
+    }
+
+    /// Applies `writes` atomically, committing `transaction` if there is one.
+    ///
+    /// The transaction is finished whether or not the commit succeeds.
+    pub fn commit(
+        &mut self,
+        transaction: Option<&[u8]>,
+        writes: Vec<Write>,
+    ) -> Result<(Vec<WriteResult>, Timestamp), Status> {
+        if let Some(id) = transaction {
+            let transaction = self
+                .transactions
+                .remove(id)
+                .ok_or_else(transaction_not_found)?;
+
+            if transaction.read_only && !writes.is_empty() {
+                return Err(Status::invalid_argument(
+                    "Cannot modify entities in a read-only transaction",
+                ));
+            }
+        }
+
+        let commit_time = self.tick();
+        let mut staged = BTreeMap::<String, Option<Document>>::new();
+        let mut results = Vec::with_capacity(writes.len());
+
+        for write in writes {
+            if !write.update_transforms.is_empty() {
+                return Err(Status::unimplemented(
+                    "Field transforms are not supported by the test server",
+                ));
+            }
+
+            let name = match &write.operation {
+                Some(Operation::Update(document)) => document.name.clone(),
+                Some(Operation::Delete(name)) => name.clone(),
+                Some(Operation::Transform(_)) => {
+                    return Err(Status::unimplemented(
+                        "Field transforms are not supported by the test server",
+                    ))
+                }
+                None => return Err(Status::invalid_argument("Write is missing an operation")),
+            };
+
+            // Our own transaction has already been removed, so any lock
+            // that's left belongs to someone else.
+            if self.transactions.values().any(|t| t.locks.contains(&name)) {
+                return Err(Status::aborted(format!(
+                    "Too much contention on these documents: {}",
+                    name
+                )));
+            }
+
+            let existing = match staged.get(&name) {
+                Some(staged) => staged.clone(),
+                None => self.documents.get(&name).cloned(),
+            };
+            check_precondition(&name, existing.as_ref(), write.current_document)?;
+
+            let updated = match write.operation {
+                Some(Operation::Update(document)) => Some(apply_update(
+                    existing,
+                    document,
+                    write.update_mask.map(|mask| mask.field_paths),
+                    &commit_time,
+                )),
+                _ => None,
+            };
+
+            results.push(WriteResult {
+                update_time: Some(commit_time.clone()),
+                transform_results: vec![],
+            });
+            staged.insert(name, updated);
+        }
+
+        for (name, document) in staged {
+            match document {
+                Some(document) => self.documents.insert(name, document),
+                None => self.documents.remove(&name),
+            };
+        }
+
+        Ok((results, commit_time))
+    }
+
+    /// Returns the current time, guaranteed to be later than any previous call.
+    pub fn tick(&mut self) -> Timestamp {
+        let mut time = Timestamp::from(SystemTime::now());
+
+        if let Some(last) = &self.last_time {
+            if (time.seconds, time.nanos) <= (last.seconds, last.nanos) {
+                time = last.clone();
+                time.nanos += 1;
+                time.normalize();
+            }
+        }
+
+        self.last_time = Some(time.clone());
+        time
+    }
+}
+
+fn apply_update(
+    existing: Option<Document>,
+    document: Document,
+    update_mask: Option<Vec<String>>,
+    commit_time: &Timestamp,
+) -> Document {
+    let create_time = existing
+        .as_ref()
+        .and_then(|existing| existing.create_time.clone())
+        .unwrap_or_else(|| commit_time.clone());
+
+    let fields = match update_mask {
+        Some(paths) => {
+            let mut fields = existing.map(|e| e.fields).unwrap_or_default();
+            for path in paths {
+                let segments = query::split_path(&path);
+                let value = query::lookup(&document.fields, &segments).cloned();
+                query::set_field(&mut fields, &segments, value);
+            }
+            fields
+        }
+        None => document.fields,
+    };
+
+    Document {
+        name: document.name,
+        fields,
+        create_time: Some(create_time),
+        update_time: Some(commit_time.clone()),
+    }
+}
+
+fn check_precondition(
+    name: &str,
+    existing: Option<&Document>,
+    precondition: Option<Precondition>,
+) -> Result<(), Status> {
+    match precondition.and_then(|p| p.condition_type) {
+        None => Ok(()),
+        Some(ConditionType::Exists(true)) if existing.is_none() => Err(Status::not_found(format!(
+            "No document to update: {}",
+            name
+        ))),
+        Some(ConditionType::Exists(false)) if existing.is_some() => Err(Status::already_exists(
+            format!("Document already exists: {}", name),
+        )),
+        Some(ConditionType::Exists(_)) => Ok(()),
+        Some(ConditionType::UpdateTime(time)) => match existing {
+            Some(document) if document.update_time.as_ref() == Some(&time) => Ok(()),
+            _ => Err(Status::failed_precondition(format!(
+                "The update time of {} doesn't match the precondition",
+                name
+            ))),
+        },
+    }
+}
+
+fn transaction_not_found() -> Status {
+    Status::invalid_argument("The referenced transaction has expired or is no longer valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NAME: &str = "projects/test/databases/(default)/documents/books/northern-lights";
+
+    fn update(name: &str) -> Write {
+        Write {
+            update_mask: None,
+            update_transforms: vec![],
+            current_document: None,
+            operation: Some(Operation::Update(Document {
+                name: name.to_string(),
+                ..Document::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn test_locks_conflict_with_writes() {
+        let mut store = Store::default();
+        let first = store.begin_transaction(false);
+        let second = store.begin_transaction(false);
+
+        store.get(Some(&first), NAME).unwrap();
+        store.get(Some(&second), NAME).unwrap();
+
+        let error = store.commit(Some(&first), vec![update(NAME)]).unwrap_err();
+        assert_eq!(error.code(), tonic::Code::Aborted);
+
+        // The failed commit released the first transactions locks
+        store.commit(Some(&second), vec![update(NAME)]).unwrap();
+        assert!(store.get(None, NAME).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_read_only_transactions_dont_lock() {
+        let mut store = Store::default();
+        let read_only = store.begin_transaction(true);
+
+        store.get(Some(&read_only), NAME).unwrap();
+        store.commit(None, vec![update(NAME)]).unwrap();
+
+        let error = store
+            .commit(Some(&read_only), vec![update(NAME)])
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_rollback_releases_locks() {
+        let mut store = Store::default();
+        let transaction = store.begin_transaction(false);
+
+        store.get(Some(&transaction), NAME).unwrap();
+        assert_eq!(
+            store.commit(None, vec![update(NAME)]).unwrap_err().code(),
+            tonic::Code::Aborted
+        );
+
+        store.rollback(&transaction).unwrap();
+        store.commit(None, vec![update(NAME)]).unwrap();
+        assert!(store.rollback(&transaction).is_err());
+    }
+
+    #[test]
+    fn test_update_mask() {
+        let mut store = Store::default();
+        let mut first = update(NAME);
+        if let Some(Operation::Update(document)) = &mut first.operation {
+            document.fields.insert("title".into(), Default::default());
+            document.fields.insert("pages".into(), Default::default());
+        }
+        store.commit(None, vec![first]).unwrap();
+
+        let mut second = update(NAME);
+        second.update_mask = Some(crate::google::firestore::v1::DocumentMask {
+            field_paths: vec!["pages".into()],
+        });
+        store.commit(None, vec![second]).unwrap();
+
+        let document = store.get(None, NAME).unwrap().unwrap();
+        assert_eq!(
+            document.fields.keys().collect::<Vec<_>>(),
+            vec![&"title".to_string()]
+        );
+        assert_ne!(document.create_time, document.update_time);
+    }
+}
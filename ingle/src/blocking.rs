@@ -0,0 +1,348 @@
+//! A synchronous wrapper around `Database`, for code that isn't async.
+//!
+//! `BlockingDatabase` owns a tokio runtime and blocks the current thread on
+//! each operation.  It mustn't be created, used or dropped from within an
+//! async runtime.
+//!
+//! Transaction closures run on tokio's blocking thread pool, so they need to
+//! be `Send + 'static`.  If a transaction times out the closure can't be
+//! interrupted - it'll keep running, but its writes won't be committed.
+
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use tokio::runtime::{Handle, Runtime};
+
+use crate::{
+    document::{Document, DocumentResponse},
+    executors::ReadExecutor,
+    operations::{
+        AddDocumentOperation, ListDocumentsOperation, ListDocumentsStream, OperationError,
+        SetDocumentOperation,
+    },
+    transactions::{
+        self, ReadOnly, ReadOnlyTransaction, ReadWrite, Transaction, TransactionBuilder,
+        TransactionError, TransactionOutcome,
+    },
+    Database, DatabaseBuilder,
+};
+
+pub struct BlockingDatabase {
+    database: Database,
+    runtime: Runtime,
+}
+
+impl BlockingDatabase {
+    /// Connects to Firestore, failing if it can't be reached.
+    pub fn connect(builder: DatabaseBuilder) -> Result<BlockingDatabase, ConnectError> {
+        let runtime = new_runtime()?;
+        let database = runtime.block_on(builder.connect())?;
+
+        Ok(BlockingDatabase { database, runtime })
+    }
+
+    /// Creates a BlockingDatabase that will connect to Firestore on its first
+    /// request.
+    pub fn connect_lazy(builder: DatabaseBuilder) -> Result<BlockingDatabase, ConnectError> {
+        let runtime = new_runtime()?;
+        let database = {
+            let _guard = runtime.enter();
+            builder.connect_lazy()?
+        };
+
+        Ok(BlockingDatabase { database, runtime })
+    }
+
+    /// The async `Database` this wraps.
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn add<T>(
+        &self,
+        operation: AddDocumentOperation<T>,
+    ) -> Result<DocumentResponse<T>, OperationError>
+    where
+        T: Document,
+    {
+        self.runtime.block_on(operation.run(&self.database))
+    }
+
+    pub fn set<T>(
+        &self,
+        operation: SetDocumentOperation<T>,
+    ) -> Result<DocumentResponse<T>, OperationError>
+    where
+        T: Document,
+    {
+        self.runtime.block_on(operation.run(&self.database))
+    }
+
+    /// Lists documents, fetching each page as the iterator reaches it.
+    pub fn list<T>(
+        &self,
+        operation: ListDocumentsOperation<T>,
+    ) -> ListDocumentsIter<'_, T, Database>
+    where
+        T: Document,
+    {
+        ListDocumentsIter::new(self.runtime.handle(), &self.database, operation)
+    }
+
+    pub fn transaction(&self) -> BlockingTransactionBuilder<'_, ReadWrite> {
+        BlockingTransactionBuilder {
+            inner: self.database.transaction(),
+            runtime: &self.runtime,
+        }
+    }
+}
+
+fn new_runtime() -> std::io::Result<Runtime> {
+    // Handle::block_on can only drive IO on a multi threaded runtime, and we
+    // need it to run reads from transaction closures.
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConnectError {
+    #[error("Couldn't start a tokio runtime: {0}")]
+    RuntimeError(#[from] std::io::Error),
+    #[error("{0}")]
+    ConnectError(#[from] crate::ConnectError),
+}
+
+/// An iterator over the documents in a collection.
+pub struct ListDocumentsIter<'a, T, E>
+where
+    E: ReadExecutor,
+{
+    handle: &'a Handle,
+    stream: Pin<Box<ListDocumentsStream<'a, T, E>>>,
+}
+
+impl<'a, T, E> ListDocumentsIter<'a, T, E>
+where
+    T: Document,
+    E: ReadExecutor,
+{
+    fn new(handle: &'a Handle, executor: &'a E, operation: ListDocumentsOperation<T>) -> Self {
+        ListDocumentsIter {
+            handle,
+            stream: Box::pin(operation.stream(executor)),
+        }
+    }
+}
+
+impl<'a, T, E> Iterator for ListDocumentsIter<'a, T, E>
+where
+    T: Document + 'a,
+    E: ReadExecutor,
+{
+    type Item = Result<DocumentResponse<T>, OperationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.handle.block_on(self.stream.next())
+    }
+}
+
+#[must_use]
+pub struct BlockingTransactionBuilder<'a, Mode> {
+    inner: TransactionBuilder<Mode>,
+    runtime: &'a Runtime,
+}
+
+impl<'a, Mode> BlockingTransactionBuilder<'a, Mode> {
+    pub fn read_only(self) -> BlockingTransactionBuilder<'a, ReadOnly> {
+        BlockingTransactionBuilder {
+            inner: self.inner.read_only(),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn read_write(self) -> BlockingTransactionBuilder<'a, ReadWrite> {
+        BlockingTransactionBuilder {
+            inner: self.inner.read_write(),
+            runtime: self.runtime,
+        }
+    }
+
+    /// See `TransactionBuilder::timeout`.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        BlockingTransactionBuilder {
+            inner: self.inner.timeout(timeout),
+            ..self
+        }
+    }
+}
+
+impl<'a> BlockingTransactionBuilder<'a, ReadWrite> {
+    pub fn max_retries(self, retries: u8) -> Self {
+        BlockingTransactionBuilder {
+            inner: self.inner.max_retries(retries),
+            ..self
+        }
+    }
+
+    /// Runs a transaction, calling `transaction` again for each retry.
+    pub fn run<F, R>(self, transaction: F) -> Result<TransactionOutcome<R>, TransactionError>
+    where
+        F: FnMut(ReadPhaseExecutor) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let transaction = BlockingTransaction {
+            transaction: Arc::new(Mutex::new(transaction)),
+            handle: self.runtime.handle().clone(),
+        };
+
+        self.runtime.block_on(self.inner.run(transaction))
+    }
+}
+
+impl<'a> BlockingTransactionBuilder<'a, ReadOnly> {
+    pub fn run<F, R>(self, transaction: F) -> Result<TransactionOutcome<R>, TransactionError>
+    where
+        F: FnOnce(ReadOnlyExecutor) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let transaction = BlockingTransaction {
+            transaction,
+            handle: self.runtime.handle().clone(),
+        };
+
+        self.runtime.block_on(self.inner.run(transaction))
+    }
+}
+
+/// Adapts a synchronous closure into a `Transaction`.
+struct BlockingTransaction<F> {
+    transaction: F,
+    handle: Handle,
+}
+
+#[async_trait]
+impl<F, R> Transaction for BlockingTransaction<Arc<Mutex<F>>>
+where
+    F: FnMut(ReadPhaseExecutor) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    type Result = R;
+
+    async fn run(&self, executor: transactions::ReadPhaseExecutor) -> R {
+        let transaction = self.transaction.clone();
+        let executor = ReadPhaseExecutor {
+            executor,
+            handle: self.handle.clone(),
+        };
+
+        spawn_blocking(move || (transaction.lock().unwrap())(executor)).await
+    }
+}
+
+#[async_trait]
+impl<F, R> ReadOnlyTransaction for BlockingTransaction<F>
+where
+    F: FnOnce(ReadOnlyExecutor) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    type Result = R;
+
+    async fn run(self, executor: transactions::ReadOnlyExecutor) -> R {
+        let transaction = self.transaction;
+        let executor = ReadOnlyExecutor {
+            executor,
+            handle: self.handle,
+        };
+
+        spawn_blocking(move || transaction(executor)).await
+    }
+}
+
+/// Runs `f` on the blocking thread pool, propagating any panics.
+async fn spawn_blocking<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => panic!("Blocking transaction failed: {}", error),
+    }
+}
+
+/// Reads documents in a read-write transaction.
+pub struct ReadPhaseExecutor {
+    executor: transactions::ReadPhaseExecutor,
+    handle: Handle,
+}
+
+impl ReadPhaseExecutor {
+    pub fn list<T>(
+        &self,
+        operation: ListDocumentsOperation<T>,
+    ) -> ListDocumentsIter<'_, T, transactions::ReadPhaseExecutor>
+    where
+        T: Document,
+    {
+        ListDocumentsIter::new(&self.handle, &self.executor, operation)
+    }
+
+    pub fn finish_reads(self) -> WritePhaseExecutor {
+        WritePhaseExecutor {
+            executor: self.executor.finish_reads(),
+            handle: self.handle,
+        }
+    }
+}
+
+/// Writes documents in a read-write transaction.
+pub struct WritePhaseExecutor {
+    executor: transactions::WritePhaseExecutor,
+    handle: Handle,
+}
+
+impl WritePhaseExecutor {
+    pub fn add<T>(&self, operation: AddDocumentOperation<T>) -> Result<(), OperationError>
+    where
+        T: Document,
+    {
+        self.handle.block_on(operation.run_in(&self.executor))
+    }
+
+    pub fn set<T>(&self, operation: SetDocumentOperation<T>) -> Result<(), OperationError>
+    where
+        T: Document,
+    {
+        self.handle.block_on(operation.run_in(&self.executor))
+    }
+
+    pub fn rollback(self) {
+        self.handle.block_on(self.executor.rollback())
+    }
+}
+
+/// Reads documents in a read-only transaction.
+pub struct ReadOnlyExecutor {
+    executor: transactions::ReadOnlyExecutor,
+    handle: Handle,
+}
+
+impl ReadOnlyExecutor {
+    pub fn list<T>(
+        &self,
+        operation: ListDocumentsOperation<T>,
+    ) -> ListDocumentsIter<'_, T, transactions::ReadOnlyExecutor>
+    where
+        T: Document,
+    {
+        ListDocumentsIter::new(&self.handle, &self.executor, operation)
+    }
+}
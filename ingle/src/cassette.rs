@@ -0,0 +1,699 @@
+//! Record & replay executors for deterministic tests.
+//!
+//! A `RecordingExecutor` wraps another executor & records every request it
+//! makes, along with the response, into a `Cassette`.  Cassettes can be saved
+//! as JSON & served back by a `ReplayExecutor`, so tests recorded once against
+//! the emulator can run offline afterwards.  Fields are saved in the proto3
+//! JSON mapping, the same as `DocumentValues::to_json`.
+//!
+//! Requests are recorded relative to the database, so a cassette can be
+//! replayed against any project.  Transaction IDs, retry policies & any error
+//! details other than the message aren't recorded.
+
+use std::{collections::VecDeque, path::Path, sync::Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    document::DocumentResponse,
+    executors::{ReadExecutor, WriteExecutor},
+    json, operations,
+    values::{DecodingError, DocumentValues},
+    ErrorDetails, FirestoreError,
+};
+
+/// A recorded sequence of requests & their responses.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Cassette, CassetteError> {
+        Cassette::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CassetteError> {
+        Ok(std::fs::write(path, self.to_json())?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Cassette, CassetteError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Pretty printed JSON, with map keys sorted so it's stable enough to
+    /// snapshot.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Cassettes to always serialize")
+    }
+
+    pub fn len(&self) -> usize {
+        self.interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interactions.is_empty()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CassetteError {
+    #[error("Could not read or write cassette: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid cassette JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Wraps an executor, recording the requests made through it.
+pub struct RecordingExecutor<E> {
+    inner: E,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl<E> RecordingExecutor<E> {
+    pub fn new(inner: E) -> Self {
+        RecordingExecutor {
+            inner,
+            interactions: Mutex::new(vec![]),
+        }
+    }
+
+    /// A cassette containing everything recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        Cassette {
+            interactions: self.interactions.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CassetteError> {
+        self.cassette().save(path)
+    }
+
+    fn record(&self, request: RecordedRequest, response: RecordedResponse) {
+        self.interactions
+            .lock()
+            .unwrap()
+            .push(Interaction { request, response });
+    }
+}
+
+#[async_trait]
+impl<E> ReadExecutor for RecordingExecutor<E>
+where
+    E: ReadExecutor,
+{
+    async fn list_documents(
+        &self,
+        input: operations::ListDocumentsRequest,
+    ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
+        let request = RecordedRequest::from_list_documents(&input);
+        let response = self.inner.list_documents(input).await;
+
+        self.record(
+            request,
+            match &response {
+                Ok(response) => RecordedResponse::from_list_documents(response),
+                Err(error) => RecordedResponse::Error(error.into()),
+            },
+        );
+
+        response
+    }
+}
+
+#[async_trait]
+impl<E> WriteExecutor for RecordingExecutor<E>
+where
+    E: WriteExecutor,
+{
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let request = RecordedRequest::from_add_document(&input);
+        let response = self.inner.add_document(input).await;
+
+        self.record(request, RecordedResponse::from_document(&response));
+
+        response
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let request = RecordedRequest::from_set_document(&input);
+        let response = self.inner.set_document(input).await;
+
+        self.record(request, RecordedResponse::from_document(&response));
+
+        response
+    }
+}
+
+/// Serves the responses from a cassette.
+///
+/// Requests must arrive in the same order they were recorded in.  Any request
+/// that doesn't match the next one in the cassette causes a panic, with both
+/// requests printed as JSON.
+pub struct ReplayExecutor {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl ReplayExecutor {
+    pub fn new(cassette: Cassette) -> Self {
+        ReplayExecutor {
+            interactions: Mutex::new(cassette.interactions.into()),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        Ok(ReplayExecutor::new(Cassette::load(path)?))
+    }
+
+    /// The number of recorded interactions that haven't been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().unwrap().len()
+    }
+
+    fn replay(&self, request: RecordedRequest) -> RecordedResponse {
+        let next = self.interactions.lock().unwrap().pop_front();
+
+        match next {
+            Some(interaction) if interaction.request == request => interaction.response,
+            Some(interaction) => panic!(
+                "Request did not match the cassette.\n\nExpected:\n{}\n\nActual:\n{}",
+                to_json(&interaction.request),
+                to_json(&request)
+            ),
+            None => panic!(
+                "Unexpected request after the end of the cassette:\n{}",
+                to_json(&request)
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl ReadExecutor for ReplayExecutor {
+    async fn list_documents(
+        &self,
+        input: operations::ListDocumentsRequest,
+    ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
+        match self.replay(RecordedRequest::from_list_documents(&input)) {
+            RecordedResponse::ListDocuments {
+                documents,
+                next_page_token,
+            } => Ok(operations::ListDocumentsResponse {
+                next_page_token,
+                documents: documents
+                    .into_iter()
+                    .map(RecordedListEntry::into_document)
+                    .collect(),
+            }),
+            RecordedResponse::Error(error) => Err(error.into()),
+            response => panic!("Cassette has the wrong response type: {:?}", response),
+        }
+    }
+}
+
+#[async_trait]
+impl WriteExecutor for ReplayExecutor {
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        self.replay(RecordedRequest::from_add_document(&input))
+            .into_document()
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        self.replay(RecordedRequest::from_set_document(&input))
+            .into_document()
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).expect("Cassettes to always serialize")
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "operation")]
+enum RecordedRequest {
+    ListDocuments {
+        collection: String,
+        page_size: i32,
+        page_token: String,
+        in_transaction: bool,
+    },
+    AddDocument {
+        collection: String,
+        document_id: String,
+        #[serde(with = "proto_fields")]
+        fields: DocumentValues,
+    },
+    SetDocument {
+        document: String,
+        #[serde(with = "proto_fields")]
+        fields: DocumentValues,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        precondition: Option<RecordedPrecondition>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedPrecondition {
+    Exists(bool),
+    UpdateTime(String),
+}
+
+impl RecordedPrecondition {
+    fn from_precondition(precondition: &operations::Precondition) -> Self {
+        match precondition {
+            operations::Precondition::Exists(exists) => RecordedPrecondition::Exists(*exists),
+            operations::Precondition::UpdateTime(time) => RecordedPrecondition::UpdateTime(
+                json::timestamp_to_json(&time.clone().into_firestore()),
+            ),
+        }
+    }
+}
+
+impl RecordedRequest {
+    fn from_list_documents(request: &operations::ListDocumentsRequest) -> Self {
+        RecordedRequest::ListDocuments {
+            collection: request.collection_path().relative_path(),
+            page_size: request.page_size(),
+            page_token: request.page_token().to_string(),
+            in_transaction: request.is_in_transaction(),
+        }
+    }
+
+    fn from_add_document(request: &operations::AddDocumentRequest) -> Self {
+        RecordedRequest::AddDocument {
+            collection: request.collection_path().relative_path(),
+            document_id: request.document_id().to_string(),
+            fields: request.document().clone(),
+        }
+    }
+
+    fn from_set_document(request: &operations::SetDocumentRequest) -> Self {
+        RecordedRequest::SetDocument {
+            document: request.document_path().relative_path().to_string(),
+            fields: request.document().clone(),
+            precondition: request
+                .precondition()
+                .map(RecordedPrecondition::from_precondition),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedResponse {
+    ListDocuments {
+        documents: Vec<RecordedListEntry>,
+        next_page_token: Option<String>,
+    },
+    Document(RecordedDocument),
+    Error(RecordedError),
+}
+
+impl RecordedResponse {
+    fn from_list_documents(response: &operations::ListDocumentsResponse<DocumentValues>) -> Self {
+        RecordedResponse::ListDocuments {
+            documents: response
+                .documents
+                .iter()
+                .map(|document| match document {
+                    Ok(document) => RecordedListEntry::Document(document.into()),
+                    Err(DecodingError::NoValuePresent) => RecordedListEntry::DecodingError {
+                        decoding_error: "no_value_present".into(),
+                    },
+                })
+                .collect(),
+            next_page_token: response.next_page_token.clone(),
+        }
+    }
+
+    fn from_document(response: &Result<DocumentResponse<DocumentValues>, FirestoreError>) -> Self {
+        match response {
+            Ok(document) => RecordedResponse::Document(document.into()),
+            Err(error) => RecordedResponse::Error(error.into()),
+        }
+    }
+
+    fn into_document(self) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        match self {
+            RecordedResponse::Document(document) => Ok(document.into()),
+            RecordedResponse::Error(error) => Err(error.into()),
+            response => panic!("Cassette has the wrong response type: {:?}", response),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RecordedListEntry {
+    Document(RecordedDocument),
+    DecodingError { decoding_error: String },
+}
+
+impl RecordedListEntry {
+    fn into_document(self) -> Result<DocumentResponse<DocumentValues>, DecodingError> {
+        match self {
+            RecordedListEntry::Document(document) => Ok(document.into()),
+            RecordedListEntry::DecodingError { .. } => Err(DecodingError::NoValuePresent),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedDocument {
+    name: String,
+    #[serde(with = "proto_fields")]
+    fields: DocumentValues,
+}
+
+impl From<&DocumentResponse<DocumentValues>> for RecordedDocument {
+    fn from(document: &DocumentResponse<DocumentValues>) -> Self {
+        RecordedDocument {
+            name: document.name.clone(),
+            fields: document.document.clone(),
+        }
+    }
+}
+
+impl From<RecordedDocument> for DocumentResponse<DocumentValues> {
+    fn from(document: RecordedDocument) -> Self {
+        DocumentResponse {
+            name: document.name,
+            document: document.fields,
+        }
+    }
+}
+
+/// Serializes fields in the same proto3 JSON mapping as `DocumentValues::to_json`.
+mod proto_fields {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value as Json;
+
+    use crate::{json, values::DocumentValues};
+
+    pub fn serialize<S>(values: &DocumentValues, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        json::fields_to_json(&values.clone().into_firestore()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DocumentValues, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields =
+            json::fields_from_json(Json::deserialize(deserializer)?).map_err(D::Error::custom)?;
+        DocumentValues::try_from_firestore(fields).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedError {
+    code: ErrorCode,
+    message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    Unknown,
+    Cancelled,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+    MalformedResponse,
+}
+
+impl From<&FirestoreError> for RecordedError {
+    fn from(error: &FirestoreError) -> Self {
+        let code = match error {
+            FirestoreError::UnknownError(_) => ErrorCode::Unknown,
+            FirestoreError::Cancelled(_) => ErrorCode::Cancelled,
+            FirestoreError::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            FirestoreError::DeadlineExceeded(_) => ErrorCode::DeadlineExceeded,
+            FirestoreError::NotFound(_) => ErrorCode::NotFound,
+            FirestoreError::AlreadyExists(_) => ErrorCode::AlreadyExists,
+            FirestoreError::PermissionDenied(_) => ErrorCode::PermissionDenied,
+            FirestoreError::ResourceExhausted(_) => ErrorCode::ResourceExhausted,
+            FirestoreError::FailedPrecondition(_) => ErrorCode::FailedPrecondition,
+            FirestoreError::Aborted(_) => ErrorCode::Aborted,
+            FirestoreError::OutOfRange(_) => ErrorCode::OutOfRange,
+            FirestoreError::Unimplemented(_) => ErrorCode::Unimplemented,
+            FirestoreError::Internal(_) => ErrorCode::Internal,
+            FirestoreError::Unavailable(_) => ErrorCode::Unavailable,
+            FirestoreError::DataLoss(_) => ErrorCode::DataLoss,
+            FirestoreError::Unauthenticated(_) => ErrorCode::Unauthenticated,
+            FirestoreError::MalformedResponse(_) => ErrorCode::MalformedResponse,
+        };
+
+        RecordedError {
+            code,
+            message: match error.details() {
+                Some(details) => details.message.clone(),
+                None => error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<RecordedError> for FirestoreError {
+    fn from(error: RecordedError) -> Self {
+        let details = Box::new(ErrorDetails {
+            message: error.message,
+            ..ErrorDetails::default()
+        });
+
+        match error.code {
+            ErrorCode::Unknown => FirestoreError::UnknownError(details),
+            ErrorCode::Cancelled => FirestoreError::Cancelled(details),
+            ErrorCode::InvalidArgument => FirestoreError::InvalidArgument(details),
+            ErrorCode::DeadlineExceeded => FirestoreError::DeadlineExceeded(details),
+            ErrorCode::NotFound => FirestoreError::NotFound(details),
+            ErrorCode::AlreadyExists => FirestoreError::AlreadyExists(details),
+            ErrorCode::PermissionDenied => FirestoreError::PermissionDenied(details),
+            ErrorCode::ResourceExhausted => FirestoreError::ResourceExhausted(details),
+            ErrorCode::FailedPrecondition => FirestoreError::FailedPrecondition(details),
+            ErrorCode::Aborted => FirestoreError::Aborted(details),
+            ErrorCode::OutOfRange => FirestoreError::OutOfRange(details),
+            ErrorCode::Unimplemented => FirestoreError::Unimplemented(details),
+            ErrorCode::Internal => FirestoreError::Internal(details),
+            ErrorCode::Unavailable => FirestoreError::Unavailable(details),
+            ErrorCode::DataLoss => FirestoreError::DataLoss(details),
+            ErrorCode::Unauthenticated => FirestoreError::Unauthenticated(details),
+            // DecodingError only has the one variant
+            ErrorCode::MalformedResponse => {
+                FirestoreError::MalformedResponse(DecodingError::NoValuePresent)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+
+    use super::*;
+    use crate::{
+        executors::tests::TestExecutor,
+        values::{Timestamp, Value},
+        CollectionRef,
+    };
+
+    fn book(title: &str) -> DocumentValues {
+        DocumentValues::from_hashmap(hashmap! {
+            "title".to_string() => Value::String(title.to_string())
+        })
+    }
+
+    fn list_response() -> operations::ListDocumentsResponse<DocumentValues> {
+        operations::ListDocumentsResponse {
+            next_page_token: None,
+            documents: vec![Ok(DocumentResponse {
+                name: "projects/test/databases/(default)/documents/books/northern-lights".into(),
+                document: book("Northern Lights"),
+            })],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recording() {
+        let executor = TestExecutor::default().list_documents_result(Ok(list_response()));
+        let recorder = RecordingExecutor::new(executor);
+
+        CollectionRef::new("books")
+            .list_documents::<DocumentValues>()
+            .page_size(10)
+            .fetch_page(&recorder)
+            .await
+            .unwrap();
+
+        CollectionRef::new("books")
+            .document("northern-lights")
+            .set(&book("Northern Lights"))
+            .exists(false)
+            .run(&recorder)
+            .await
+            .unwrap_err();
+
+        insta::assert_snapshot!(recorder.cassette().to_json(), @r###"
+        {
+          "interactions": [
+            {
+              "request": {
+                "operation": "list_documents",
+                "collection": "/books",
+                "page_size": 10,
+                "page_token": "",
+                "in_transaction": false
+              },
+              "response": {
+                "list_documents": {
+                  "documents": [
+                    {
+                      "name": "projects/test/databases/(default)/documents/books/northern-lights",
+                      "fields": {
+                        "title": {
+                          "stringValue": "Northern Lights"
+                        }
+                      }
+                    }
+                  ],
+                  "next_page_token": null
+                }
+              }
+            },
+            {
+              "request": {
+                "operation": "set_document",
+                "document": "/books/northern-lights",
+                "fields": {
+                  "title": {
+                    "stringValue": "Northern Lights"
+                  }
+                },
+                "precondition": {
+                  "exists": false
+                }
+              },
+              "response": {
+                "error": {
+                  "code": "unknown",
+                  "message": ""
+                }
+              }
+            }
+          ]
+        }
+        "###);
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let recorder = RecordingExecutor::new(
+            TestExecutor::default().list_documents_result(Ok(list_response())),
+        );
+        let books = CollectionRef::new("books");
+
+        let recorded = books
+            .list_documents::<DocumentValues>()
+            .fetch_all(&recorder)
+            .await
+            .unwrap();
+
+        let cassette = Cassette::from_json(&recorder.cassette().to_json()).unwrap();
+        assert_eq!(cassette, recorder.cassette());
+
+        let replay = ReplayExecutor::new(cassette);
+        let replayed = books
+            .list_documents::<DocumentValues>()
+            .fetch_all(&replay)
+            .await
+            .unwrap();
+
+        assert_eq!(replayed, recorded);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Request did not match the cassette")]
+    async fn test_replay_mismatch() {
+        let recorder = RecordingExecutor::new(
+            TestExecutor::default().list_documents_result(Ok(list_response())),
+        );
+        CollectionRef::new("books")
+            .list_documents::<DocumentValues>()
+            .fetch_all(&recorder)
+            .await
+            .unwrap();
+
+        let replay = ReplayExecutor::new(recorder.cassette());
+        CollectionRef::new("authors")
+            .list_documents::<DocumentValues>()
+            .fetch_all(&replay)
+            .await
+            .ok();
+    }
+
+    #[test]
+    fn test_fields_round_trip() {
+        let document = RecordedDocument {
+            name: "projects/test/databases/(default)/documents/books/northern-lights".into(),
+            fields: DocumentValues::from_hashmap(hashmap! {
+                "published".to_string() => Value::Timestamp(Timestamp { seconds: 1, nandos: 2 }),
+                "tags".to_string() => Value::Array(vec![Value::Null, Value::Double(1.5)]),
+            }),
+        };
+
+        let json = serde_json::to_string(&document).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<RecordedDocument>(&json).unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    fn test_invalid_fields_fail_to_load() {
+        let json = r#"{"interactions": [{
+            "request": {
+                "operation": "set_document",
+                "document": "/books/northern-lights",
+                "fields": {"title": {"stringValue": 1}}
+            },
+            "response": {"error": {"code": "unknown", "message": ""}}
+        }]}"#;
+
+        assert!(Cassette::from_json(json).is_err());
+    }
+}
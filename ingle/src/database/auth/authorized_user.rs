@@ -0,0 +1,100 @@
+//! Credentials for a user authorized via `gcloud auth application-default login`.
+//!
+//! These exchange a long lived refresh token for short lived access tokens.
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    http::HttpClient,
+    oauth::{TokenResponse, DEFAULT_TOKEN_URI},
+    provider::{AccessToken, CredentialsProvider},
+};
+
+/// JSON schema of an `authorized_user` credentials file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthorizedUserCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+    pub quota_project_id: Option<String>,
+
+    /// The endpoint to exchange the refresh token at.
+    ///
+    /// This isn't usually present in the file, in which case Googles OAuth2
+    /// token endpoint is used.
+    #[serde(default)]
+    pub token_uri: Option<String>,
+}
+
+pub struct AuthorizedUser {
+    credentials: AuthorizedUserCredentials,
+    http: HttpClient,
+}
+
+impl AuthorizedUser {
+    pub fn new(credentials: AuthorizedUserCredentials, http: HttpClient) -> Self {
+        AuthorizedUser { credentials, http }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialsProvider for AuthorizedUser {
+    async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>> {
+        let token_uri = self
+            .credentials
+            .token_uri
+            .as_deref()
+            .unwrap_or(DEFAULT_TOKEN_URI);
+
+        let response = self
+            .http
+            .post_form::<TokenResponse>(
+                token_uri,
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("client_id", &self.credentials.client_id),
+                    ("client_secret", &self.credentials.client_secret),
+                    ("refresh_token", &self.credentials.refresh_token),
+                ],
+            )
+            .await?;
+
+        Ok(response.into_access_token(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::auth::oauth::tests::serve_json;
+
+    #[tokio::test]
+    async fn test_refresh_token_exchange() {
+        let (address, requests) = serve_json(json!({
+            "access_token": "user-token",
+            "expires_in": 3599,
+            "scope": "https://www.googleapis.com/auth/cloud-platform",
+            "token_type": "Bearer"
+        }));
+
+        let user = AuthorizedUser::new(
+            AuthorizedUserCredentials {
+                client_id: "client".into(),
+                client_secret: "secret".into(),
+                refresh_token: "refresh".into(),
+                quota_project_id: None,
+                token_uri: Some(format!("http://{}/token", address)),
+            },
+            HttpClient::default(),
+        );
+
+        assert_eq!(user.token().await.unwrap().token, "user-token");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,72 @@
+//! Reading credentials files of any supported type.
+
+use std::{fs, io, path::Path};
+
+use super::{authorized_user::AuthorizedUserCredentials, token::Credentials};
+
+pub enum CredentialsFile {
+    ServiceAccount(Credentials),
+    AuthorizedUser(AuthorizedUserCredentials),
+}
+
+impl CredentialsFile {
+    /// The project the credentials belong to, if they specify one.
+    pub fn project_id(&self) -> Option<&str> {
+        match self {
+            CredentialsFile::ServiceAccount(credentials) => credentials.project_id.as_deref(),
+            CredentialsFile::AuthorizedUser(credentials) => credentials.quota_project_id.as_deref(),
+        }
+    }
+}
+
+/// Reads a credentials file, using its `type` field to determine what kind of
+/// credentials it contains.
+pub fn read_credentials_file(path: impl AsRef<Path>) -> io::Result<CredentialsFile> {
+    let contents = fs::read(path)?;
+    let json: serde_json::Value = serde_json::from_slice(&contents).map_err(invalid_data)?;
+
+    match json.get("type").and_then(|t| t.as_str()) {
+        None | Some("service_account") => Ok(CredentialsFile::ServiceAccount(
+            serde_json::from_value(json).map_err(invalid_data)?,
+        )),
+        Some("authorized_user") => Ok(CredentialsFile::AuthorizedUser(
+            serde_json::from_value(json).map_err(invalid_data)?,
+        )),
+        Some(other) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported credentials type: {}", other),
+        )),
+    }
+}
+
+fn invalid_data(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_service_account() {
+        let file = read_credentials_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/database/auth/service-account-test.json"
+        ))
+        .unwrap();
+
+        assert!(matches!(file, CredentialsFile::ServiceAccount(_)));
+    }
+
+    #[test]
+    fn test_reading_authorized_user() {
+        let file = read_credentials_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/database/auth/test-gcloud-config/application_default_credentials.json"
+        ))
+        .unwrap();
+
+        assert!(matches!(file, CredentialsFile::AuthorizedUser(_)));
+        assert_eq!(file.project_id(), Some("test-project"));
+    }
+}
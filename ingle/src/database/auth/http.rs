@@ -0,0 +1,118 @@
+//! A minimal HTTP client for talking to token endpoints.
+
+use std::sync::{Arc, RwLock};
+
+use hyper::{client::HttpConnector, header, Body, Client, Method, Request, StatusCode};
+use hyper_rustls::HttpsConnector;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Clones share their TLS configuration, so `DatabaseBuilder` can hand one
+/// to each credentials provider & configure TLS for all of them on connect.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Arc<RwLock<Client<HttpsConnector<HttpConnector>>>>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient {
+            client: Arc::new(RwLock::new(
+                Client::builder().build(HttpsConnector::with_webpki_roots()),
+            )),
+        }
+    }
+}
+
+impl HttpClient {
+    /// Makes every clone of this client use `tls` for HTTPS requests.
+    pub fn set_tls_config(&self, tls: rustls::ClientConfig) {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        *self.client.write().unwrap() = Client::builder().build((http, tls).into());
+    }
+
+    /// GETs `url` with the given headers and decodes the JSON response.
+    pub async fn get<T>(&self, url: &str, headers: &[(&str, &str)]) -> Result<T, HttpError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut request = Request::builder().method(Method::GET).uri(url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        self.send(request.body(Body::empty())?).await
+    }
+
+    /// POSTs a form encoded body to `url` and decodes the JSON response.
+    pub async fn post_form<T>(&self, url: &str, form: &[(&str, &str)]) -> Result<T, HttpError>
+    where
+        T: DeserializeOwned,
+    {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form)
+            .finish();
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))?;
+
+        self.send(request).await
+    }
+
+    /// POSTs a JSON body to `url` with the given headers and decodes the JSON response.
+    pub async fn post_json<T>(
+        &self,
+        url: &str,
+        body: &impl Serialize,
+        headers: &[(&str, &str)],
+    ) -> Result<T, HttpError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header(header::CONTENT_TYPE, "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        self.send(request.body(Body::from(serde_json::to_vec(body)?))?)
+            .await
+    }
+
+    async fn send<T>(&self, request: Request<Body>) -> Result<T, HttpError>
+    where
+        T: DeserializeOwned,
+    {
+        let client = self.client.read().unwrap().clone();
+        let response = client.request(request).await?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        if !status.is_success() {
+            return Err(HttpError::UnsuccessfulResponse {
+                status,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum HttpError {
+    #[error("Couldn't build HTTP request: {0}")]
+    InvalidRequest(#[from] hyper::http::Error),
+    #[error("HTTP error: {0}")]
+    TransportError(#[from] hyper::Error),
+    #[error("Got a {status} response: {body}")]
+    UnsuccessfulResponse { status: StatusCode, body: String },
+    #[error("Couldn't decode response: {0}")]
+    MalformedResponse(#[from] serde_json::Error),
+}
@@ -0,0 +1,70 @@
+//! Credentials from the GCE metadata server.
+//!
+//! Code running on GCE, GKE, Cloud Run or Cloud Functions can fetch access
+//! tokens for its attached service account from the metadata server, without
+//! needing a key file.
+
+use std::time::Instant;
+
+use super::{
+    http::HttpClient,
+    oauth::TokenResponse,
+    provider::{AccessToken, CredentialsProvider},
+};
+
+pub static DEFAULT_METADATA_HOST: &str = "metadata.google.internal";
+static TOKEN_PATH: &str = "/computeMetadata/v1/instance/service-accounts/default/token";
+
+pub struct MetadataServer {
+    host: String,
+    http: HttpClient,
+}
+
+impl MetadataServer {
+    /// Creates credentials that fetch tokens from the metadata server at `host`.
+    pub fn new(host: impl Into<String>, http: HttpClient) -> Self {
+        MetadataServer {
+            host: host.into(),
+            http,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialsProvider for MetadataServer {
+    async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .http
+            .get::<TokenResponse>(
+                &format!("http://{}{}", self.host, TOKEN_PATH),
+                &[("Metadata-Flavor", "Google")],
+            )
+            .await?;
+
+        Ok(response.into_access_token(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::auth::oauth::tests::serve_json;
+
+    #[tokio::test]
+    async fn test_metadata_server_token() {
+        let (address, requests) = serve_json(json!({
+            "access_token": "metadata-token",
+            "expires_in": 3599,
+            "token_type": "Bearer"
+        }));
+
+        let server = MetadataServer::new(address.to_string(), HttpClient::default());
+
+        assert_eq!(server.token().await.unwrap().token, "metadata-token");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
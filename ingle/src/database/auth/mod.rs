@@ -1,20 +1,94 @@
+use std::sync::Arc;
+
+use tonic::codegen::http::HeaderValue;
+
+mod authorized_user;
+mod file;
+mod http;
+mod impersonation;
+mod metadata;
+mod oauth;
+mod provider;
+mod refresh;
 mod service;
 mod token;
 
+pub use authorized_user::{AuthorizedUser, AuthorizedUserCredentials};
+pub use file::{read_credentials_file, CredentialsFile};
+pub use http::HttpClient;
+pub use impersonation::{ImpersonatedCredentials, Impersonation};
+pub use metadata::{MetadataServer, DEFAULT_METADATA_HOST};
+pub use oauth::{ServiceAccountOAuth, CLOUD_PLATFORM_SCOPE, DATASTORE_SCOPE};
+pub use provider::{AccessToken, CredentialsProvider};
+pub use refresh::RefreshingToken;
 pub use service::AuthService;
-pub use token::Token;
+pub use token::{credentials_from_file, Credentials as ServiceAccountCredentials, Token};
+
+#[cfg(test)]
+pub(crate) use oauth::tests::serve_json;
 
 #[derive(Clone)]
 pub enum Credentials {
-    ServiceAccount(Token),
+    ServiceAccount(RefreshingToken, Arc<ServiceAccountCredentials>),
+    ServiceAccountOAuth(RefreshingToken, Arc<ServiceAccountCredentials>),
+    AuthorizedUser(RefreshingToken),
+    MetadataServer(RefreshingToken),
+    Provider(RefreshingToken),
     AuthToken(String),
     EmulatorOwner,
 }
 
+impl Credentials {
+    /// Returns the value of the authorization header for these credentials.
+    pub async fn authorization(&self) -> Result<HeaderValue, tonic::Status> {
+        let token = match self {
+            Credentials::ServiceAccount(token, _)
+            | Credentials::ServiceAccountOAuth(token, _)
+            | Credentials::AuthorizedUser(token)
+            | Credentials::MetadataServer(token)
+            | Credentials::Provider(token) => token.token().await.map_err(|e| {
+                tonic::Status::unauthenticated(format!("Couldn't get an access token: {}", e))
+            })?,
+            Credentials::AuthToken(token) => token.clone(),
+            Credentials::EmulatorOwner => "owner".to_string(),
+        };
+
+        HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| {
+            tonic::Status::unauthenticated("Access token contained invalid characters")
+        })
+    }
+
+    /// Returns credentials suitable for calling the IAM Credentials API with.
+    ///
+    /// Service account tokens for Firestore can't be used there, so we switch
+    /// those to OAuth2 tokens with the cloud-platform scope.
+    pub fn for_impersonation(self, http: HttpClient) -> Credentials {
+        match self {
+            Credentials::ServiceAccount(_, key) | Credentials::ServiceAccountOAuth(_, key) => {
+                Credentials::ServiceAccountOAuth(
+                    RefreshingToken::new(ServiceAccountOAuth::new(
+                        (*key).clone(),
+                        CLOUD_PLATFORM_SCOPE,
+                        http,
+                    )),
+                    key,
+                )
+            }
+            other => other,
+        }
+    }
+}
+
 impl std::fmt::Debug for Credentials {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Credentials::ServiceAccount(_) => write!(f, "Credentials::ServiceAccount(_)"),
+            Credentials::ServiceAccount(..) => write!(f, "Credentials::ServiceAccount(_)"),
+            Credentials::ServiceAccountOAuth(..) => {
+                write!(f, "Credentials::ServiceAccountOAuth(_)")
+            }
+            Credentials::AuthorizedUser(_) => write!(f, "Credentials::AuthorizedUser(_)"),
+            Credentials::MetadataServer(_) => write!(f, "Credentials::MetadataServer(_)"),
+            Credentials::Provider(_) => write!(f, "Credentials::Provider(_)"),
             Credentials::AuthToken(_) => write!(f, "Credentials::AuthToken(_)"),
             Credentials::EmulatorOwner => write!(f, "Credentials::EmulatorOwner"),
         }
@@ -0,0 +1,198 @@
+//! OAuth2 access tokens for service accounts.
+//!
+//! Rather than sending a self-signed JWT with every request, this signs a JWT
+//! assertion and exchanges it at the service accounts `token_uri` for an access
+//! token.  See https://developers.google.com/identity/protocols/oauth2/service-account
+
+use std::time::{Duration, Instant, SystemTime};
+
+use frank_jwt as jwt;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    http::HttpClient,
+    provider::{AccessToken, CredentialsProvider},
+    token::Credentials,
+};
+
+pub static DATASTORE_SCOPE: &str = "https://www.googleapis.com/auth/datastore";
+pub static CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+pub static DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+static JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+static ASSERTION_VALID_TIME: Duration = Duration::from_secs(60 * 60);
+
+pub struct ServiceAccountOAuth {
+    credentials: Credentials,
+    scope: String,
+    http: HttpClient,
+}
+
+impl ServiceAccountOAuth {
+    pub fn new(credentials: Credentials, scope: impl Into<String>, http: HttpClient) -> Self {
+        ServiceAccountOAuth {
+            credentials,
+            scope: scope.into(),
+            http,
+        }
+    }
+
+    fn token_uri(&self) -> &str {
+        self.credentials
+            .token_uri
+            .as_deref()
+            .unwrap_or(DEFAULT_TOKEN_URI)
+    }
+
+    /// Signs a JWT assertion.  RSA signing is slow, so this happens on a
+    /// blocking thread.
+    async fn assertion(&self, token_uri: &str) -> Result<String, TokenError> {
+        let email = &self.credentials.client_email;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Something fucky is happening with system time");
+
+        let claims = json!({
+            "iss": &email,
+            "scope": &self.scope,
+            "aud": token_uri,
+            "iat": now.as_secs(),
+            "exp": (now + ASSERTION_VALID_TIME).as_secs()
+        });
+
+        let private_key = self.credentials.private_key.clone();
+        tokio::task::spawn_blocking(move || {
+            jwt::encode(json!({}), &private_key, &claims, jwt::Algorithm::RS256)
+        })
+        .await
+        .map_err(|e| TokenError::JwtError(e.to_string()))?
+        .map_err(TokenError::from)
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialsProvider for ServiceAccountOAuth {
+    async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>> {
+        let token_uri = self.token_uri();
+        let assertion = self.assertion(token_uri).await?;
+
+        let response = self
+            .http
+            .post_form::<TokenResponse>(
+                token_uri,
+                &[
+                    ("grant_type", JWT_BEARER_GRANT_TYPE),
+                    ("assertion", &assertion),
+                ],
+            )
+            .await?;
+
+        Ok(response.into_access_token(Instant::now()))
+    }
+}
+
+/// The JSON response from an OAuth2 token endpoint.
+#[derive(Deserialize)]
+pub struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+impl TokenResponse {
+    pub fn into_access_token(self, received_at: Instant) -> AccessToken {
+        AccessToken {
+            token: self.access_token,
+            expires_at: received_at + Duration::from_secs(self.expires_in),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TokenError {
+    #[error("Error encoding a JWT from credentials: {0}")]
+    JwtError(String),
+}
+
+impl From<frank_jwt::Error> for TokenError {
+    fn from(e: frank_jwt::Error) -> Self {
+        TokenError::JwtError(e.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        convert::Infallible,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server,
+    };
+
+    use super::*;
+
+    /// Starts a local HTTP server that responds to every request with `body`.
+    ///
+    /// Returns the servers address & a count of the requests it's received.
+    pub fn serve_json(body: serde_json::Value) -> (SocketAddr, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let body = body.to_string();
+
+        let make_service = {
+            let requests = Arc::clone(&requests);
+            make_service_fn(move |_| {
+                let requests = Arc::clone(&requests);
+                let body = body.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                        requests.fetch_add(1, Ordering::SeqCst);
+                        let body = body.clone();
+                        async move { Ok::<_, Infallible>(Response::new(Body::from(body))) }
+                    }))
+                }
+            })
+        };
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        (address, requests)
+    }
+
+    fn credentials(token_uri: String) -> Credentials {
+        let mut credentials: Credentials =
+            serde_json::from_str(include_str!("service-account-test.json")).unwrap();
+        credentials.token_uri = Some(token_uri);
+        credentials
+    }
+
+    #[tokio::test]
+    async fn test_assertion_exchange() {
+        let (address, requests) = serve_json(json!({
+            "access_token": "a-token",
+            "expires_in": 3600,
+            "token_type": "Bearer"
+        }));
+
+        let oauth = ServiceAccountOAuth::new(
+            credentials(format!("http://{}/token", address)),
+            DATASTORE_SCOPE,
+            HttpClient::default(),
+        );
+
+        let token = oauth.token().await.unwrap();
+
+        assert_eq!(token.token, "a-token");
+        assert!(token.expires_at > Instant::now() + Duration::from_secs(3500));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,38 @@
+//! The `CredentialsProvider` trait.
+
+use std::time::{Duration, Instant};
+
+/// How long before expiry we'll start refreshing an access token in the background.
+static REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// A bearer token along with when it expires.
+#[derive(Clone, Debug)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+impl AccessToken {
+    pub(super) fn is_expiring(&self) -> bool {
+        self.expires_at.saturating_duration_since(Instant::now()) < REFRESH_MARGIN
+    }
+
+    pub(super) fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+/// A source of bearer tokens for authenticating with Firestore.
+///
+/// Implement this to use credentials that ingle doesn't support out of the
+/// box - e.g. tokens issued by Vault or Firebase ID tokens when testing
+/// security rules - and pass it to `DatabaseBuilder::credentials_provider`.
+///
+/// Tokens are cached until shortly before `expires_at`, at which point `token`
+/// will be called in the background to fetch a replacement.  Only one call to
+/// `token` will be in flight at a time.
+#[async_trait::async_trait]
+pub trait CredentialsProvider: Send + Sync + 'static {
+    /// Fetches a new token.
+    async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>>;
+}
@@ -0,0 +1,184 @@
+//! Caching & background refresh of access tokens.
+
+use std::sync::{Arc, Mutex};
+
+use super::provider::{AccessToken, CredentialsProvider};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Caches the tokens from a `CredentialsProvider`, refreshing them ahead of expiry.
+///
+/// Once a token is close to expiry we return it as normal but start fetching
+/// its replacement in the background.  If the token has actually expired the
+/// caller has to wait for the replacement.  Only one fetch is ever in flight at
+/// a time - concurrent callers share it.
+#[derive(Clone)]
+pub struct RefreshingToken {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    source: Box<dyn CredentialsProvider>,
+    cached: Mutex<Option<AccessToken>>,
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl RefreshingToken {
+    pub fn new(source: impl CredentialsProvider) -> Self {
+        RefreshingToken::with_initial_token(source, None)
+    }
+
+    /// Creates a RefreshingToken that starts off with `token` in its cache.
+    pub fn with_initial_token(
+        source: impl CredentialsProvider,
+        token: Option<AccessToken>,
+    ) -> Self {
+        RefreshingToken {
+            inner: Arc::new(Inner {
+                source: Box::new(source),
+                cached: Mutex::new(token),
+                refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
+            }),
+        }
+    }
+
+    /// Returns a valid access token, fetching one if we don't have one.
+    pub async fn token(&self) -> Result<String, BoxError> {
+        if let Some(token) = self.inner.cached() {
+            if !token.is_expired() {
+                if token.is_expiring() {
+                    self.refresh_in_background();
+                }
+                return Ok(token.token);
+            }
+        }
+
+        let _guard = self.inner.refresh_lock.lock().await;
+
+        // Someone else might have refreshed while we were waiting for the lock.
+        if let Some(token) = self.inner.cached() {
+            if !token.is_expired() {
+                return Ok(token.token);
+            }
+        }
+
+        self.inner.refresh().await
+    }
+
+    fn refresh_in_background(&self) {
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+
+        let guard = match Arc::clone(&self.inner.refresh_lock).try_lock_owned() {
+            Ok(guard) => guard,
+            // There's already a refresh in flight
+            Err(_) => return,
+        };
+
+        let inner = Arc::clone(&self.inner);
+        handle.spawn(async move {
+            // If this fails we'll try again on the next request.
+            inner.refresh().await.ok();
+            drop(guard);
+        });
+    }
+}
+
+impl Inner {
+    fn cached(&self) -> Option<AccessToken> {
+        self.cached.lock().unwrap().clone()
+    }
+
+    /// Fetches a new token & caches it.  Callers should hold the refresh_lock.
+    async fn refresh(&self) -> Result<String, BoxError> {
+        let token = self.source.token().await?;
+        let result = token.token.clone();
+        *self.cached.lock().unwrap() = Some(token);
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    struct CountingSource {
+        fetches: Arc<AtomicUsize>,
+        expires_in: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl CredentialsProvider for CountingSource {
+        async fn token(&self) -> Result<AccessToken, BoxError> {
+            let count = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(AccessToken {
+                token: format!("token-{}", count),
+                expires_at: Instant::now() + self.expires_in,
+            })
+        }
+    }
+
+    fn refreshing_token(expires_in: Duration) -> (RefreshingToken, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let token = RefreshingToken::new(CountingSource {
+            fetches: Arc::clone(&fetches),
+            expires_in,
+        });
+        (token, fetches)
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached() {
+        let (token, fetches) = refreshing_token(Duration::from_secs(3600));
+
+        assert_eq!(token.token().await.unwrap(), "token-1");
+        assert_eq!(token.token().await.unwrap(), "token-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_a_fetch() {
+        let (token, fetches) = refreshing_token(Duration::from_secs(3600));
+
+        let (a, b, c) = tokio::join!(token.token(), token.token(), token.token());
+
+        assert_eq!(a.unwrap(), "token-1");
+        assert_eq!(b.unwrap(), "token-1");
+        assert_eq!(c.unwrap(), "token-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_token_is_refreshed_in_background() {
+        let (token, fetches) = refreshing_token(Duration::from_secs(60));
+
+        assert_eq!(token.token().await.unwrap(), "token-1");
+
+        // The expiring token is still returned while the refresh happens.
+        assert_eq!(token.token().await.unwrap(), "token-1");
+        assert_eq!(token.token().await.unwrap(), "token-1");
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        assert_eq!(token.token().await.unwrap(), "token-2");
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_waits_for_refresh() {
+        let (token, fetches) = refreshing_token(Duration::from_secs(0));
+
+        assert_eq!(token.token().await.unwrap(), "token-1");
+        assert_eq!(token.token().await.unwrap(), "token-2");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,7 +1,12 @@
 //! Authentication service.
 
 use std::task::{Context, Poll};
-use tonic::codegen::http::{header::AUTHORIZATION, HeaderValue, Request};
+
+use futures_util::future::BoxFuture;
+use tonic::codegen::{
+    http::{header::AUTHORIZATION, Request},
+    StdError,
+};
 use tower_service::Service;
 
 use super::Credentials;
@@ -21,42 +26,84 @@ impl<S> AuthService<S> {
 
 impl<S, Body, Response> Service<Request<Body>> for AuthService<S>
 where
-    S: Service<Request<Body>, Response = Response>,
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Error: Into<StdError>,
+    S::Future: Send,
+    Body: Send + 'static,
 {
     type Response = S::Response;
-    type Error = S::Error;
-    type Future = S::Future;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<S::Response, StdError>>;
 
     #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
+        self.inner.poll_ready(cx).map_err(Into::into)
     }
 
-    #[inline]
     fn call(&mut self, mut request: Request<Body>) -> Self::Future {
-        match &self.credentials {
-            Some(Credentials::ServiceAccount(token)) => {
-                let jwt = token.jwt();
-                request.headers_mut().insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("Bearer {}", jwt)).unwrap(),
-                );
-            }
-            Some(Credentials::AuthToken(token)) => {
-                request.headers_mut().insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-                );
-            }
-            Some(Credentials::EmulatorOwner) => {
-                request.headers_mut().insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str("Bearer owner").unwrap(),
-                );
+        // The inner service has been driven to readiness, so we need to use it
+        // rather than the clone. See https://github.com/tower-rs/tower/issues/547
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let credentials = self.credentials.clone();
+
+        Box::pin(async move {
+            if let Some(credentials) = credentials {
+                // tonic recognises a boxed Status and returns it as is, so this
+                // surfaces as an Unauthenticated error.
+                let authorization = credentials.authorization().await?;
+                request.headers_mut().insert(AUTHORIZATION, authorization);
             }
-            _ => {}
+
+            inner.call(request).await.map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{ready, Ready};
+    use tonic::codegen::http::HeaderValue;
+
+    use super::*;
+
+    /// A service that responds with the authorization header it was sent.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Option<HeaderValue>;
+        type Error = StdError;
+        type Future = Ready<Result<Self::Response, StdError>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), StdError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            ready(Ok(request.headers().get(AUTHORIZATION).cloned()))
         }
+    }
+
+    #[tokio::test]
+    async fn test_sets_authorization_header() {
+        let mut service = AuthService::new(Echo, Some(Credentials::EmulatorOwner));
+
+        let header = service.call(Request::new(())).await.unwrap();
+
+        assert_eq!(header.unwrap(), "Bearer owner");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token_is_unauthenticated() {
+        let mut service = AuthService::new(
+            Echo,
+            Some(Credentials::AuthToken("not\na valid header".into())),
+        );
+
+        let error = service.call(Request::new(())).await.unwrap_err();
+        let status = error.downcast::<tonic::Status>().unwrap();
 
-        self.inner.call(request)
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
     }
 }
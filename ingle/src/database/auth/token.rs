@@ -1,7 +1,6 @@
 use std::{
     fs, io,
     path::Path,
-    sync::{Arc, Mutex},
     time::{Duration, Instant, SystemTime},
 };
 
@@ -9,68 +8,35 @@ use frank_jwt as jwt;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use super::{
+    oauth::TokenError,
+    provider::{AccessToken, CredentialsProvider},
+};
+
+static JWT_VALID_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// Self-signed JWTs for a service account.
+///
+/// Signing is CPU bound, so when fetched asynchronously it happens on the
+/// blocking thread pool.
 #[derive(Clone)]
 pub struct Token {
     audience: String,
     credentials: Credentials,
-    inner: Arc<Mutex<TokenInner>>,
 }
 
 impl Token {
-    pub fn jwt(&self) -> String {
-        let mut inner = self.inner.lock().unwrap();
-
-        if inner.is_expiring() {
-            // Note: calling unwrap here as we've already validated the audience & credentials
-            // in Token::new so if this fails then something really bad is happening.
-            *inner =
-                TokenInner::generate(&self.audience, &self.credentials, Instant::now()).unwrap();
-        }
-
-        inner.jwt.clone()
-    }
-
-    pub fn new(
-        audience: impl ToString,
-        credentials: Credentials,
-    ) -> Result<Self, frank_jwt::Error> {
-        let audience = audience.to_string();
-        let inner = TokenInner::generate(&audience, &credentials, Instant::now())?;
-
-        Ok(Token {
-            audience,
+    pub fn new(audience: impl ToString, credentials: Credentials) -> Self {
+        Token {
+            audience: audience.to_string(),
             credentials,
-            inner: Arc::new(Mutex::new(inner)),
-        })
-    }
-
-    pub fn from_file(
-        path: impl AsRef<Path>,
-        audience: impl ToString,
-    ) -> Result<Self, frank_jwt::Error> {
-        Token::new(audience, credentials_from_file(path)?)
-    }
-}
-
-static JWT_VALID_TIME: Duration = Duration::from_secs(10 * 60);
-static JWT_REFRESH_TIME: Duration = Duration::from_secs(9 * 60);
-
-struct TokenInner {
-    jwt: String,
-    encoded_at: Instant,
-}
-
-impl TokenInner {
-    pub fn is_expiring(&self) -> bool {
-        self.encoded_at.elapsed() >= JWT_REFRESH_TIME
+        }
     }
 
-    pub fn generate(
-        audience: &str,
-        credentials: &Credentials,
-        now: Instant,
-    ) -> Result<Self, frank_jwt::Error> {
-        let email = &credentials.client_email;
+    /// Signs a new JWT.
+    pub fn sign(&self) -> Result<AccessToken, frank_jwt::Error> {
+        let now = Instant::now();
+        let email = &self.credentials.client_email;
 
         let now_timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -85,25 +51,36 @@ impl TokenInner {
         let claims = json!({
             "sub": &email,
             "iss": &email,
-            "aud": &audience,
+            "aud": &self.audience,
             "iat": now_timestamp,
             "exp": expires_at_timestamp
         });
 
         let header = json!({});
 
-        Ok(TokenInner {
-            jwt: jwt::encode(
+        Ok(AccessToken {
+            token: jwt::encode(
                 header,
-                &credentials.private_key,
+                &self.credentials.private_key,
                 &claims,
                 jwt::Algorithm::RS256,
             )?,
-            encoded_at: now,
+            expires_at: now + JWT_VALID_TIME,
         })
     }
 }
 
+#[async_trait::async_trait]
+impl CredentialsProvider for Token {
+    async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>> {
+        let token = self.clone();
+        tokio::task::spawn_blocking(move || token.sign())
+            .await
+            .map_err(|e| TokenError::JwtError(e.to_string()))?
+            .map_err(|e| TokenError::from(e).into())
+    }
+}
+
 /// JSON schema of the GOOGLE_APPLICATION_CREDENTIALS file.
 ///
 /// You can use `credentials_from_file()` as a quick way to read the JSON
@@ -137,30 +114,13 @@ pub fn credentials_from_file<S: AsRef<Path>>(path: S) -> io::Result<Credentials>
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_is_expiring() {
+    #[tokio::test]
+    async fn test_token() {
         let creds = serde_json::from_str(include_str!("service-account-test.json")).unwrap();
+        let token = Token::new("test-audience", creds).token().await.unwrap();
 
-        let token = TokenInner::generate(
-            "test-audience",
-            &creds,
-            Instant::now()
-                .checked_sub(Duration::from_secs(8 * 60))
-                .unwrap(),
-        )
-        .unwrap();
-
-        assert!(!token.is_expiring());
-
-        let token = TokenInner::generate(
-            "test-audience",
-            &creds,
-            Instant::now()
-                .checked_sub(Duration::from_secs(9 * 60))
-                .unwrap(),
-        )
-        .unwrap();
-
-        assert!(token.is_expiring());
+        assert!(!token.token.is_empty());
+        assert!(token.expires_at > Instant::now() + Duration::from_secs(9 * 60));
+        assert!(token.expires_at <= Instant::now() + JWT_VALID_TIME);
     }
 }
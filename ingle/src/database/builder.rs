@@ -1,10 +1,26 @@
-use std::time::Duration;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use tonic::transport::{self, ClientTlsConfig, Endpoint};
+use std::convert::TryInto;
+
+use tonic::{
+    codegen::http::{self, Uri},
+    transport::{self, Endpoint},
+};
 
 use super::{
-    auth::{AuthService, Credentials, Token},
-    Database,
+    auth::{
+        credentials_from_file, read_credentials_file, AuthService, AuthorizedUser,
+        AuthorizedUserCredentials, Credentials, CredentialsFile, CredentialsProvider, HttpClient,
+        ImpersonatedCredentials, Impersonation, MetadataServer, RefreshingToken,
+        ServiceAccountOAuth, Token, DATASTORE_SCOPE, DEFAULT_METADATA_HOST,
+    },
+    layer::{box_layer, BoxLayer, BoxService, LayeredService},
+    metrics::{Metrics, MetricsRecorder},
+    pool::{Balancing, ChannelPool},
+    rest::{RestService, Transport},
+    routing::{InvalidRoutingHeader, RoutingHeaders},
+    tls::TlsConfig,
+    Database, RetryPolicy,
 };
 use crate::google::firestore::v1::firestore_client::FirestoreClient;
 use crate::paths::ProjectPath;
@@ -14,39 +30,163 @@ static FIRESTORE_TOKEN_AUDIENCE: &str =
     "https://firestore.googleapis.com/google.firestore.v1beta1.Firestore";
 static DEFAULT_DATABASE: &str = "(default)";
 
+static EMULATOR_HOST_VAR: &str = "FIRESTORE_EMULATOR_HOST";
+static CREDENTIALS_FILE_VAR: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+static PROJECT_ID_VARS: &[&str] = &["GOOGLE_CLOUD_PROJECT", "GCLOUD_PROJECT"];
+static GCLOUD_CONFIG_DIR_VAR: &str = "CLOUDSDK_CONFIG";
+static METADATA_HOST_VAR: &str = "GCE_METADATA_HOST";
+static WELL_KNOWN_CREDENTIALS_FILE: &str = "application_default_credentials.json";
+
 pub struct DatabaseBuilder {
-    endpoint: Endpoint,
+    endpoint: Result<Uri, String>,
+    tcp_keepalive: Option<Duration>,
+    timeout: Option<Duration>,
     credentials: Option<Credentials>,
+    impersonation: Option<Impersonation>,
     project_id: String,
     database_id: String,
+    retry_policy: RetryPolicy,
+    tls: TlsConfig,
+    http: HttpClient,
+    pool_size: usize,
+    balancing: Balancing,
+    layers: Vec<BoxLayer>,
+    metrics: Metrics,
+    transport: Transport,
 }
 
 impl DatabaseBuilder {
     pub fn new(project_id: impl Into<String>) -> DatabaseBuilder {
         DatabaseBuilder {
-            endpoint: Endpoint::from_static(FIRESTORE_ENDPOINT)
-                .tls_config(ClientTlsConfig::default())
-                .expect("Couldn't configure TLS"),
+            endpoint: Ok(Uri::from_static(FIRESTORE_ENDPOINT)),
+            tcp_keepalive: None,
+            timeout: None,
             credentials: None,
+            impersonation: None,
             project_id: project_id.into(),
             database_id: DEFAULT_DATABASE.to_string(),
+            retry_policy: RetryPolicy::default(),
+            tls: TlsConfig::default(),
+            http: HttpClient::default(),
+            pool_size: 1,
+            balancing: Balancing::RoundRobin,
+            layers: vec![],
+            metrics: Metrics::default(),
+            transport: Transport::default(),
         }
     }
 
-    pub fn https_endpoint(self, url: &str) -> Self {
-        DatabaseBuilder {
-            endpoint: Endpoint::from_shared(format!("https://{}", url))
-                .expect("Invalid firestore URL")
-                .tls_config(ClientTlsConfig::default())
-                .expect("Couldn't initialise TLS"),
-            ..self
+    /// Configures a DatabaseBuilder from the environment.
+    ///
+    /// If `FIRESTORE_EMULATOR_HOST` is set this will connect to the emulator at
+    /// that host over plaintext HTTP/2 using owner credentials.  Otherwise it'll
+    /// connect to Firestore using the same credentials as `default_credentials`.
+    ///
+    /// The project ID is read from `GOOGLE_CLOUD_PROJECT` or `GCLOUD_PROJECT`,
+    /// falling back to the project in the credentials file.  If there's no
+    /// credentials file but a project ID is set, credentials will be fetched
+    /// from the metadata server.
+    pub fn from_env() -> Result<DatabaseBuilder, FromEnvError> {
+        DatabaseBuilder::from_lookup(|name| Ok(env_var(name)?))
+    }
+
+    fn from_lookup(
+        lookup: impl Fn(&'static str) -> Result<Option<String>, FromEnvError>,
+    ) -> Result<DatabaseBuilder, FromEnvError> {
+        let mut project_id = None;
+        for var in PROJECT_ID_VARS {
+            project_id = lookup(var)?;
+            if project_id.is_some() {
+                break;
+            }
+        }
+
+        if let Some(host) = lookup(EMULATOR_HOST_VAR)? {
+            let project_id = project_id.ok_or_else(|| FromEnvError::MissingProjectId {
+                checked: PROJECT_ID_VARS.to_vec(),
+            })?;
+
+            return Ok(DatabaseBuilder::new(project_id)
+                .http_endpoint(&host)
+                .emulator_owner_credentials());
         }
+
+        let path = match (find_credentials_file(&lookup)?, &project_id) {
+            (Ok(path), _) => path,
+            (Err(_), Some(project_id)) => {
+                let host = lookup(METADATA_HOST_VAR)?;
+                return Ok(
+                    DatabaseBuilder::new(project_id.clone()).metadata_server_credentials(host)
+                );
+            }
+            (Err(mut checked), None) => {
+                checked.insert(0, EMULATOR_HOST_VAR.to_string());
+                return Err(FromEnvError::NoCredentials { checked });
+            }
+        };
+
+        let credentials =
+            read_credentials_file(&path).map_err(|error| FromEnvError::UnreadableCredentials {
+                path: path.display().to_string(),
+                error,
+            })?;
+
+        let project_id = project_id
+            .or_else(|| credentials.project_id().map(str::to_string))
+            .ok_or_else(|| {
+                let mut checked = PROJECT_ID_VARS.to_vec();
+                checked.push(CREDENTIALS_FILE_VAR);
+                FromEnvError::MissingProjectId { checked }
+            })?;
+
+        Ok(DatabaseBuilder::new(project_id).credentials_file(credentials)?)
+    }
+
+    /// Sets the URL of the Firestore endpoint to connect to.
+    ///
+    /// This should be a full `http` or `https` URL, e.g. `https://host:port`,
+    /// or a `Uri`.  An invalid URL will cause `connect` to fail.
+    pub fn endpoint<U>(self, url: U) -> Self
+    where
+        U: TryInto<Uri>,
+        U::Error: Into<http::Error>,
+    {
+        let endpoint = url
+            .try_into()
+            .map_err(|e| e.into().to_string())
+            .and_then(validate_endpoint);
+
+        DatabaseBuilder { endpoint, ..self }
     }
 
-    pub fn http_endpoint(self, url: &str) -> Self {
+    /// Connects to `host` over HTTPS, where `host` is a host & optional port.
+    pub fn https_endpoint(self, host: &str) -> Self {
+        self.endpoint(format!("https://{}", host))
+    }
+
+    /// Connects to `host` over plaintext HTTP, where `host` is a host &
+    /// optional port.
+    pub fn http_endpoint(self, host: &str) -> Self {
+        self.endpoint(format!("http://{}", host))
+    }
+
+    /// Sets the TLS configuration used for HTTPS endpoints.
+    ///
+    /// This also applies to requests for access tokens, apart from the domain
+    /// name override.  Any problems with the configuration will be returned
+    /// from `connect`.
+    pub fn tls_config(self, tls: TlsConfig) -> Self {
+        DatabaseBuilder { tls, ..self }
+    }
+
+    /// Sets the ID of the database to connect to.
+    ///
+    /// Defaults to `(default)`.  Other IDs must be 4-63 characters long, made up
+    /// of lowercase letters, numbers & hyphens, start with a letter and not end
+    /// with a hyphen.  An invalid ID will cause `connect` to fail.
+    pub fn database_id(self, database_id: impl Into<String>) -> Self {
         DatabaseBuilder {
-            endpoint: Endpoint::from_shared(format!("http://{}", url))
-                .expect("Invalid firestore URL"),
+            database_id: database_id.into(),
             ..self
         }
     }
@@ -60,7 +200,7 @@ impl DatabaseBuilder {
     /// Default is no keepalive (None)
     pub fn tcp_keepalive(self, duration: impl Into<Option<Duration>>) -> Self {
         DatabaseBuilder {
-            endpoint: self.endpoint.tcp_keepalive(duration.into()),
+            tcp_keepalive: duration.into(),
             ..self
         }
     }
@@ -68,31 +208,144 @@ impl DatabaseBuilder {
     /// Apply a timeout to each request.
     pub fn timeout(self, duration: Duration) -> Self {
         DatabaseBuilder {
-            endpoint: self.endpoint.timeout(duration),
+            timeout: Some(duration),
             ..self
         }
     }
 
+    /// Sets the policy used to retry idempotent requests that fail with a
+    /// transient error, and to retry the initial connection.
+    ///
+    /// This can be overridden for individual operations.  Defaults to
+    /// `RetryPolicy::default()`.
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        DatabaseBuilder {
+            retry_policy,
+            ..self
+        }
+    }
+
+    /// Loads the application default credentials.
+    ///
+    /// These are read from the file pointed at by `GOOGLE_APPLICATION_CREDENTIALS`,
+    /// or the file `gcloud auth application-default login` writes if that's not
+    /// set.  Both service account and authorized user credentials are supported.
+    ///
+    /// If neither file exists we fall back to fetching tokens from the metadata
+    /// server, which is available on GCE, GKE & Cloud Run.
     pub fn default_credentials(self) -> Result<Self, DefaultCredentialsError> {
-        let filename = match std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
-            Ok(filename) => filename,
-            Err(std::env::VarError::NotPresent) => {
-                return Err(DefaultCredentialsError::MissingEnvVar)
-            }
-            Err(std::env::VarError::NotUnicode(_)) => {
-                return Err(DefaultCredentialsError::MalformedEnvVar)
+        let path = match find_credentials_file(&env_var)? {
+            Ok(path) => path,
+            Err(_) => {
+                let host = env_var(METADATA_HOST_VAR)?;
+                return Ok(self.metadata_server_credentials(host));
             }
         };
 
+        self.credentials_file(read_credentials_file(path)?)
+    }
+
+    /// Authenticates with access tokens from the GCE metadata server.
+    ///
+    /// If `host` is None then `metadata.google.internal` is used.
+    pub fn metadata_server_credentials(self, host: Option<String>) -> Self {
+        let host = host.unwrap_or_else(|| DEFAULT_METADATA_HOST.to_string());
+        DatabaseBuilder {
+            credentials: Some(Credentials::MetadataServer(RefreshingToken::new(
+                MetadataServer::new(host, self.http.clone()),
+            ))),
+            ..self
+        }
+    }
+
+    /// Authenticates as a user with a refresh token.
+    ///
+    /// The refresh token will be exchanged for access tokens at the credentials
+    /// `token_uri`, or Googles OAuth2 token endpoint if that's not set.
+    pub fn authorized_user_credentials(self, credentials: AuthorizedUserCredentials) -> Self {
+        DatabaseBuilder {
+            credentials: Some(Credentials::AuthorizedUser(RefreshingToken::new(
+                AuthorizedUser::new(credentials, self.http.clone()),
+            ))),
+            ..self
+        }
+    }
+
+    fn credentials_file(self, file: CredentialsFile) -> Result<Self, DefaultCredentialsError> {
+        match file {
+            CredentialsFile::ServiceAccount(credentials) => {
+                self.service_account_credentials(credentials)
+            }
+            CredentialsFile::AuthorizedUser(credentials) => {
+                Ok(self.authorized_user_credentials(credentials))
+            }
+        }
+    }
+
+    /// Authenticates with OAuth2 access tokens for the service account in the
+    /// given credentials file.
+    ///
+    /// Unlike `default_credentials` this exchanges a signed JWT at the service
+    /// accounts `token_uri` for an access token with the datastore scope.  The
+    /// access token is cached and refreshed shortly before it expires.
+    pub fn service_account_oauth(
+        self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, DefaultCredentialsError> {
+        let credentials = credentials_from_file(path)?;
+
+        Ok(DatabaseBuilder {
+            credentials: Some(Credentials::ServiceAccountOAuth(
+                RefreshingToken::new(ServiceAccountOAuth::new(
+                    credentials.clone(),
+                    DATASTORE_SCOPE,
+                    self.http.clone(),
+                )),
+                Arc::new(credentials),
+            )),
+            ..self
+        })
+    }
+
+    fn service_account_credentials(
+        self,
+        credentials: super::auth::ServiceAccountCredentials,
+    ) -> Result<Self, DefaultCredentialsError> {
+        // Signing a JWT up front lets us report invalid credentials here
+        // rather than on the first request.
+        let token = Token::new(FIRESTORE_TOKEN_AUDIENCE, credentials.clone());
+        let initial_token = token.sign()?;
+
         Ok(DatabaseBuilder {
-            credentials: Some(Credentials::ServiceAccount(Token::from_file(
-                filename,
-                FIRESTORE_TOKEN_AUDIENCE,
-            )?)),
+            credentials: Some(Credentials::ServiceAccount(
+                RefreshingToken::with_initial_token(token, Some(initial_token)),
+                Arc::new(credentials),
+            )),
             ..self
         })
     }
 
+    /// Authenticates with tokens from a custom `CredentialsProvider`.
+    pub fn credentials_provider(self, provider: impl CredentialsProvider) -> Self {
+        DatabaseBuilder {
+            credentials: Some(Credentials::Provider(RefreshingToken::new(provider))),
+            ..self
+        }
+    }
+
+    /// Impersonates a service account, using the other credentials configured
+    /// on this builder to authenticate with the IAM Credentials API.
+    ///
+    /// The source credentials need permission to create tokens for the target
+    /// service account, or the first of its delegates.  They can be set before
+    /// or after calling this, but `connect` will fail if there aren't any.
+    pub fn impersonate(self, impersonation: Impersonation) -> Self {
+        DatabaseBuilder {
+            impersonation: Some(impersonation),
+            ..self
+        }
+    }
+
     pub fn emulator_owner_credentials(self) -> Self {
         DatabaseBuilder {
             credentials: Some(Credentials::EmulatorOwner),
@@ -107,29 +360,212 @@ impl DatabaseBuilder {
         }
     }
 
+    /// Sets how many gRPC channels to spread requests across.
+    ///
+    /// Each channel is a separate HTTP/2 connection, which has a limit on the
+    /// number of concurrent requests.  Busy services may want several.
+    ///
+    /// Default is 1.
+    pub fn pool_size(self, pool_size: usize) -> Self {
+        DatabaseBuilder {
+            pool_size: pool_size.max(1),
+            ..self
+        }
+    }
+
+    /// Sets how requests are spread across the channels in the pool.
+    ///
+    /// Default is `Balancing::RoundRobin`.
+    pub fn balancing(self, balancing: Balancing) -> Self {
+        DatabaseBuilder { balancing, ..self }
+    }
+
+    /// Wraps every request to Firestore in a tower `Layer`.
+    ///
+    /// This can be used for timeouts, concurrency & rate limits, custom
+    /// headers etc.  Layers wrap authentication, so will see the authorization
+    /// header.  The first layer added is the outermost.
+    pub fn layer<L>(self, layer: L) -> Self
+    where
+        L: tower::Layer<BoxService> + Send + Sync + 'static,
+        L::Service: tower_service::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<hyper::Body>,
+            > + Clone
+            + Send
+            + 'static,
+        <L::Service as tower_service::Service<http::Request<tonic::body::BoxBody>>>::Error:
+            Into<tonic::codegen::StdError>,
+        <L::Service as tower_service::Service<http::Request<tonic::body::BoxBody>>>::Future:
+            Send + 'static,
+    {
+        let mut layers = self.layers;
+        layers.push(box_layer(layer));
+
+        DatabaseBuilder { layers, ..self }
+    }
+
+    /// Sends metrics about requests, latency & documents read, written or
+    /// deleted to `recorder`.
+    pub fn metrics(self, recorder: impl MetricsRecorder + 'static) -> Self {
+        DatabaseBuilder {
+            metrics: Metrics::new(Arc::new(recorder)),
+            ..self
+        }
+    }
+
+    /// Sets whether to talk to Firestore over gRPC or the REST API.
+    ///
+    /// Default is `Transport::Grpc`.
+    pub fn transport(self, transport: Transport) -> Self {
+        DatabaseBuilder { transport, ..self }
+    }
+
+    /// Connects to Firestore, failing if it can't be reached.
+    ///
+    /// The initial connection is retried according to the retry policy.  The
+    /// REST transport connects on demand, so this behaves like `connect_lazy`.
     #[allow(clippy::redundant_closure)]
     pub async fn connect(self) -> Result<Database, ConnectError> {
-        // TODO: Probably want retries at some point?
-        let channel = self.endpoint.connect().await?;
-        let credentials = self.credentials;
+        if self.transport == Transport::Rest {
+            return self.connect_rest();
+        }
+
+        let endpoint = self.build_endpoint()?;
+        let endpoint = &endpoint;
+
+        let mut channels = Vec::with_capacity(self.pool_size);
+        for _ in 0..self.pool_size {
+            channels.push(
+                self.retry_policy
+                    .retry_if(|| endpoint.connect(), is_transient, |_| None)
+                    .await?,
+            );
+        }
+
+        let pool = ChannelPool::new(channels, self.balancing);
+        self.into_database(pool)
+    }
+
+    /// Creates a Database that will connect to Firestore on its first request.
+    ///
+    /// Unlike `connect` this won't fail if Firestore is unreachable - any
+    /// connection errors will be returned from requests instead.  This must be
+    /// called from within a tokio runtime.
+    pub fn connect_lazy(self) -> Result<Database, ConnectError> {
+        if self.transport == Transport::Rest {
+            return self.connect_rest();
+        }
+
+        let endpoint = self.build_endpoint()?;
+
+        let channels = (0..self.pool_size)
+            .map(|_| endpoint.connect_lazy())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pool = ChannelPool::new(channels, self.balancing);
+        self.into_database(pool)
+    }
+
+    fn connect_rest(self) -> Result<Database, ConnectError> {
+        validate_database_id(&self.database_id)?;
+
+        let uri = self
+            .endpoint
+            .clone()
+            .map_err(ConnectError::InvalidEndpoint)?;
+        let tls = self
+            .tls
+            .clone()
+            .into_rustls_config()
+            .map_err(ConnectError::InvalidRestTlsConfig)?;
+
+        let service = RestService::new(&uri, tls, self.tcp_keepalive, self.timeout);
+        self.into_database(service)
+    }
+
+    fn build_endpoint(&self) -> Result<Endpoint, ConnectError> {
+        validate_database_id(&self.database_id)?;
+
+        let uri = self
+            .endpoint
+            .clone()
+            .map_err(ConnectError::InvalidEndpoint)?;
+        let is_https = uri.scheme_str() == Some("https");
+
+        let mut endpoint = Endpoint::from(uri).tcp_keepalive(self.tcp_keepalive);
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if is_https {
+            endpoint = endpoint
+                .tls_config(self.tls.clone().into_client_config())
+                .map_err(ConnectError::InvalidTlsConfig)?;
+        }
+
+        Ok(endpoint)
+    }
 
-        let service = AuthService::new(channel, credentials);
+    /// The credentials to send with requests, impersonating if asked to.
+    fn build_credentials(&self) -> Result<Option<Credentials>, ConnectError> {
+        let impersonation = match &self.impersonation {
+            Some(impersonation) => impersonation.clone(),
+            None => return Ok(self.credentials.clone()),
+        };
+        let source = self
+            .credentials
+            .clone()
+            .ok_or(ConnectError::NoImpersonationSource)?;
+
+        Ok(Some(Credentials::Provider(RefreshingToken::new(
+            ImpersonatedCredentials::new(
+                source.for_impersonation(self.http.clone()),
+                impersonation,
+                self.http.clone(),
+            ),
+        ))))
+    }
+
+    fn into_database<S>(self, service: S) -> Result<Database, ConnectError>
+    where
+        S: tower_service::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<hyper::Body>,
+            > + Clone
+            + Send
+            + 'static,
+        S::Error: Into<tonic::codegen::StdError>,
+        S::Future: Send,
+    {
+        let credentials = self.build_credentials()?;
+        if credentials.is_some() {
+            let tls = self.tls.clone().into_token_config();
+            self.http
+                .set_tls_config(tls.map_err(ConnectError::InvalidTokenTlsConfig)?);
+        }
+        let project_path = ProjectPath::new(self.project_id, self.database_id);
+        let service = LayeredService::new(
+            AuthService::new(RoutingHeaders::new(service, &project_path)?, credentials),
+            &self.layers,
+        );
 
         Ok(Database {
             client: FirestoreClient::new(service),
-            project_path: ProjectPath::new(self.project_id, self.database_id),
+            project_path,
+            retry_policy: self.retry_policy,
+            metrics: self.metrics,
         })
     }
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum DefaultCredentialsError {
-    #[error("The GOOGLE_APPLICATION_CREDENTIALS environment variable could not be loaded")]
-    MissingEnvVar,
-    #[error("The GOOGLE_APPLICATION_CREDENTIALS environment variable was not valid unicode")]
-    MalformedEnvVar,
+    #[error("The {0} environment variable was not valid unicode")]
+    MalformedEnvVar(&'static str),
     #[error("Error encoding a JWT from credentials: {0}")]
     JwtError(String),
+    #[error("Couldn't read credentials file: {0}")]
+    UnreadableFile(#[from] std::io::Error),
 }
 
 impl From<frank_jwt::Error> for DefaultCredentialsError {
@@ -138,10 +574,481 @@ impl From<frank_jwt::Error> for DefaultCredentialsError {
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum FromEnvError {
+    #[error("Couldn't find credentials. Checked {checked:?}")]
+    NoCredentials { checked: Vec<String> },
+    #[error("Couldn't find a project ID. Checked {checked:?}")]
+    MissingProjectId { checked: Vec<&'static str> },
+    #[error("The {0} environment variable was not valid unicode")]
+    MalformedEnvVar(&'static str),
+    #[error("Couldn't read credentials from {path}: {error}")]
+    UnreadableCredentials { path: String, error: std::io::Error },
+    #[error("Error loading credentials: {0}")]
+    CredentialsError(#[from] DefaultCredentialsError),
+}
+
+/// Finds the application default credentials file.
+///
+/// This is either the file named by `GOOGLE_APPLICATION_CREDENTIALS` or the
+/// well known file written by gcloud, if it exists.  If neither does this
+/// returns the places that were checked.
+fn find_credentials_file<E>(
+    lookup: &impl Fn(&'static str) -> Result<Option<String>, E>,
+) -> Result<Result<PathBuf, Vec<String>>, E> {
+    if let Some(path) = lookup(CREDENTIALS_FILE_VAR)? {
+        return Ok(Ok(PathBuf::from(path)));
+    }
+
+    let mut checked = vec![CREDENTIALS_FILE_VAR.to_string()];
+
+    let gcloud_config_dir = match lookup(GCLOUD_CONFIG_DIR_VAR)? {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None if cfg!(windows) => lookup("APPDATA")?.map(|dir| PathBuf::from(dir).join("gcloud")),
+        None => lookup("HOME")?.map(|dir| PathBuf::from(dir).join(".config").join("gcloud")),
+    };
+
+    if let Some(dir) = gcloud_config_dir {
+        let path = dir.join(WELL_KNOWN_CREDENTIALS_FILE);
+        if path.exists() {
+            return Ok(Ok(path));
+        }
+        checked.push(path.display().to_string());
+    }
+
+    Ok(Err(checked))
+}
+
+/// An environment variable that wasn't valid unicode.
+struct MalformedEnvVar(&'static str);
+
+impl From<MalformedEnvVar> for FromEnvError {
+    fn from(MalformedEnvVar(name): MalformedEnvVar) -> Self {
+        FromEnvError::MalformedEnvVar(name)
+    }
+}
+
+impl From<MalformedEnvVar> for DefaultCredentialsError {
+    fn from(MalformedEnvVar(name): MalformedEnvVar) -> Self {
+        DefaultCredentialsError::MalformedEnvVar(name)
+    }
+}
+
+fn env_var(name: &'static str) -> Result<Option<String>, MalformedEnvVar> {
+    match std::env::var(name) {
+        Ok(value) if value.is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(MalformedEnvVar(name)),
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ConnectError {
     #[error("Error encoding JWT from credentials: {0}")]
     JwtError(String),
     #[error("gRPC transport error: {0}")]
     TransportError(#[from] transport::Error),
+    #[error("Invalid TLS configuration: {0}")]
+    InvalidTlsConfig(transport::Error),
+    #[error("Invalid TLS configuration: {0}")]
+    InvalidRestTlsConfig(String),
+    #[error("Invalid TLS configuration for fetching tokens: {0}")]
+    InvalidTokenTlsConfig(String),
+    #[error("Invalid database ID: {0}")]
+    InvalidDatabaseId(String),
+    #[error("Invalid endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("{0}")]
+    InvalidRoutingHeader(#[from] InvalidRoutingHeader),
+    #[error("Impersonation needs other credentials to authenticate with")]
+    NoImpersonationSource,
+}
+
+/// Whether a connection error might go away if we try again, e.g. because
+/// the server isn't accepting connections yet.  Errors like a bad TLS
+/// configuration or certificate aren't worth retrying.
+fn is_transient(error: &transport::Error) -> bool {
+    use std::{error::Error, io::ErrorKind};
+
+    let mut source = error.source();
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            return matches!(
+                error.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::AddrNotAvailable
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::UnexpectedEof
+            );
+        }
+        if let Some(error) = error.downcast_ref::<hyper::Error>() {
+            if error.is_timeout() {
+                return true;
+            }
+        }
+        source = error.source();
+    }
+
+    false
+}
+
+fn validate_endpoint(uri: Uri) -> Result<Uri, String> {
+    match uri.scheme_str() {
+        Some("http") | Some("https") => {}
+        Some(scheme) => return Err(format!("unsupported scheme {} in {}", scheme, uri)),
+        None => return Err(format!("{} is missing a scheme", uri)),
+    }
+
+    if uri.host().is_none() {
+        return Err(format!("{} is missing a host", uri));
+    }
+
+    Ok(uri)
+}
+
+fn validate_database_id(id: &str) -> Result<(), ConnectError> {
+    if id == DEFAULT_DATABASE {
+        return Ok(());
+    }
+
+    let valid_length = (4..=63).contains(&id.len());
+    let valid_chars = id
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    let valid_start = id.starts_with(|c: char| c.is_ascii_lowercase());
+    let valid_end = !id.ends_with('-');
+
+    if valid_length && valid_chars && valid_start && valid_end {
+        Ok(())
+    } else {
+        Err(ConnectError::InvalidDatabaseId(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{credentials::AccessToken, database::auth::serve_json};
+
+    fn lookup(
+        vars: HashMap<&'static str, &'static str>,
+    ) -> impl Fn(&'static str) -> Result<Option<String>, FromEnvError> {
+        move |name| Ok(vars.get(name).map(|value| value.to_string()))
+    }
+
+    struct StaticProvider;
+
+    #[async_trait::async_trait]
+    impl CredentialsProvider for StaticProvider {
+        async fn token(&self) -> Result<AccessToken, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(AccessToken {
+                token: "provided-token".into(),
+                expires_at: std::time::Instant::now() + Duration::from_secs(3600),
+            })
+        }
+    }
+
+    async fn connect_error(endpoint: &str) -> transport::Error {
+        DatabaseBuilder::new("test-project")
+            .endpoint(endpoint)
+            .build_endpoint()
+            .unwrap()
+            .connect()
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn test_refused_connections_are_transient() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let error = connect_error(&format!("http://127.0.0.1:{}", port)).await;
+
+        assert!(is_transient(&error), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn test_tls_errors_are_not_transient() {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n").ok();
+            std::thread::sleep(Duration::from_secs(1));
+        });
+
+        let error = connect_error(&format!("https://localhost:{}", port)).await;
+
+        assert!(!is_transient(&error), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_provider() {
+        let builder = DatabaseBuilder::new("test-project").credentials_provider(StaticProvider);
+
+        let authorization = builder.credentials.unwrap().authorization().await.unwrap();
+
+        assert_eq!(authorization, "Bearer provided-token");
+    }
+
+    #[tokio::test]
+    async fn test_impersonation() {
+        let (address, requests) = serve_json(serde_json::json!({
+            "accessToken": "impersonated-token",
+            "expireTime": "2021-08-01T12:00:00Z"
+        }));
+        let impersonation = Impersonation::new("target@my-project.iam.gserviceaccount.com")
+            .base_url(format!("http://{}", address));
+
+        // The source credentials can be set either side of impersonate
+        for builder in [
+            DatabaseBuilder::new("test-project")
+                .auth_token("source-token")
+                .impersonate(impersonation.clone()),
+            DatabaseBuilder::new("test-project")
+                .impersonate(impersonation.clone())
+                .auth_token("source-token"),
+        ] {
+            let credentials = builder.build_credentials().unwrap().unwrap();
+            let authorization = credentials.authorization().await.unwrap();
+
+            assert_eq!(authorization, "Bearer impersonated-token");
+        }
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_without_credentials() {
+        let error = DatabaseBuilder::new("test-project")
+            .http_endpoint("localhost:1")
+            .impersonate(Impersonation::new(
+                "target@my-project.iam.gserviceaccount.com",
+            ))
+            .connect_lazy()
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ConnectError::NoImpersonationSource));
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let builder = DatabaseBuilder::new("test-project").endpoint("https://localhost:8443");
+        assert_eq!(builder.endpoint.unwrap(), "https://localhost:8443/");
+
+        let builder =
+            DatabaseBuilder::new("test-project").endpoint(Uri::from_static("http://localhost"));
+        assert_eq!(builder.endpoint.unwrap(), "http://localhost/");
+
+        let builder = DatabaseBuilder::new("test-project").http_endpoint("localhost:8080");
+        assert_eq!(builder.endpoint.unwrap(), "http://localhost:8080/");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_endpoints() {
+        for url in &[
+            "not a url",
+            "localhost:8080",
+            "ftp://localhost",
+            "/firestore",
+        ] {
+            let error = DatabaseBuilder::new("test-project")
+                .endpoint(*url)
+                .connect()
+                .await
+                .err()
+                .unwrap();
+
+            assert!(
+                matches!(error, ConnectError::InvalidEndpoint(_)),
+                "{} should be invalid",
+                url
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_lazy() {
+        // Nothing should be listening on port 1, but we shouldn't find that out
+        // until we make a request.
+        let database = DatabaseBuilder::new("test-project")
+            .http_endpoint("localhost:1")
+            .pool_size(4)
+            .connect_lazy();
+
+        assert!(database.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_tls_config() {
+        let error = DatabaseBuilder::new("test-project")
+            .tls_config(TlsConfig::new().client_identity_pem("not a cert", "not a key"))
+            .connect()
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ConnectError::InvalidTlsConfig(_)));
+    }
+
+    #[tokio::test]
+    async fn test_tls_config_applies_to_tokens() {
+        // The endpoint doesn't use TLS, so only fetching tokens can fail here
+        let error = DatabaseBuilder::new("test-project")
+            .http_endpoint("localhost:1")
+            .tls_config(TlsConfig::new().client_identity_pem("not a cert", "not a key"))
+            .metadata_server_credentials(None)
+            .connect_lazy()
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ConnectError::InvalidTokenTlsConfig(_)));
+    }
+
+    #[test]
+    fn test_validate_database_id() {
+        for id in &["(default)", "test", "my-database", "db-2"] {
+            assert!(validate_database_id(id).is_ok(), "{} should be valid", id);
+        }
+
+        for id in &["", "db", "My-Database", "2nd-db", "database-", "my_db"] {
+            assert!(
+                matches!(
+                    validate_database_id(id),
+                    Err(ConnectError::InvalidDatabaseId(_))
+                ),
+                "{} should be invalid",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_env_with_emulator() {
+        let builder = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "FIRESTORE_EMULATOR_HOST" => "localhost:8080",
+            "GCLOUD_PROJECT" => "test-project",
+        }))
+        .unwrap();
+
+        assert_eq!(builder.project_id, "test-project");
+        assert_eq!(
+            builder.endpoint.unwrap().to_string(),
+            "http://localhost:8080/"
+        );
+        assert!(matches!(
+            builder.credentials,
+            Some(Credentials::EmulatorOwner)
+        ));
+    }
+
+    #[test]
+    fn test_from_env_prefers_google_cloud_project() {
+        let builder = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "FIRESTORE_EMULATOR_HOST" => "localhost:8080",
+            "GOOGLE_CLOUD_PROJECT" => "preferred",
+            "GCLOUD_PROJECT" => "other",
+        }))
+        .unwrap();
+
+        assert_eq!(builder.project_id, "preferred");
+    }
+
+    #[test]
+    fn test_from_env_with_service_account() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/database/auth/service-account-test.json"
+        );
+
+        let builder = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "GOOGLE_APPLICATION_CREDENTIALS" => path,
+            "GOOGLE_CLOUD_PROJECT" => "test-project",
+        }))
+        .unwrap();
+
+        assert_eq!(builder.project_id, "test-project");
+        assert_eq!(
+            builder.endpoint.unwrap().to_string(),
+            "https://firestore.googleapis.com/"
+        );
+        assert!(matches!(
+            builder.credentials,
+            Some(Credentials::ServiceAccount(..))
+        ));
+    }
+
+    #[test]
+    fn test_from_env_missing_project() {
+        let error = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "FIRESTORE_EMULATOR_HOST" => "localhost:8080",
+        }))
+        .err()
+        .unwrap();
+
+        insta::assert_snapshot!(error.to_string(), @r###"Couldn't find a project ID. Checked ["GOOGLE_CLOUD_PROJECT", "GCLOUD_PROJECT"]"###);
+    }
+
+    #[test]
+    fn test_from_env_missing_credentials() {
+        let error = DatabaseBuilder::from_lookup(lookup(HashMap::new()))
+            .err()
+            .unwrap();
+
+        insta::assert_snapshot!(error.to_string(), @r###"Couldn't find credentials. Checked ["FIRESTORE_EMULATOR_HOST", "GOOGLE_APPLICATION_CREDENTIALS"]"###);
+    }
+
+    #[test]
+    fn test_from_env_with_well_known_file() {
+        let builder = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "CLOUDSDK_CONFIG" => concat!(env!("CARGO_MANIFEST_DIR"), "/src/database/auth/test-gcloud-config"),
+        }))
+        .unwrap();
+
+        assert_eq!(builder.project_id, "test-project");
+        assert!(matches!(
+            builder.credentials,
+            Some(Credentials::AuthorizedUser(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_env_with_metadata_server() {
+        let builder = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "GOOGLE_CLOUD_PROJECT" => "test-project",
+            "GCE_METADATA_HOST" => "localhost:8081",
+        }))
+        .unwrap();
+
+        assert_eq!(builder.project_id, "test-project");
+        assert!(matches!(
+            builder.credentials,
+            Some(Credentials::MetadataServer(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_env_missing_well_known_file() {
+        let error = DatabaseBuilder::from_lookup(lookup(maplit::hashmap! {
+            "CLOUDSDK_CONFIG" => "/nonexistent",
+        }))
+        .err()
+        .unwrap();
+
+        insta::assert_snapshot!(error.to_string(), @r###"Couldn't find credentials. Checked ["FIRESTORE_EMULATOR_HOST", "GOOGLE_APPLICATION_CREDENTIALS", "/nonexistent/application_default_credentials.json"]"###);
+    }
 }
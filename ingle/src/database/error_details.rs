@@ -0,0 +1,404 @@
+//! Structured details attached to errors returned from Firestore.
+//!
+//! See https://github.com/googleapis/googleapis/blob/master/google/rpc/error_details.proto
+//! for the definitions these are decoded from.
+
+use std::{collections::HashMap, time::Duration};
+
+use prost::Message;
+
+use crate::google::rpc;
+
+static TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+/// The message & any structured details that came along with an error.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorDetails {
+    /// The developer facing error message.
+    pub message: String,
+
+    /// The cause of the error, if provided.
+    pub error_info: Option<ErrorInfo>,
+
+    /// How long the server asked us to wait before retrying, if provided.
+    pub retry_delay: Option<Duration>,
+
+    /// Any fields in the request that were invalid.
+    pub field_violations: Vec<FieldViolation>,
+
+    /// Any quota checks that failed.
+    pub quota_violations: Vec<QuotaViolation>,
+
+    /// Any preconditions that failed.
+    pub precondition_violations: Vec<PreconditionViolation>,
+
+    /// The resource that was being accessed, if provided.
+    pub resource_info: Option<ResourceInfo>,
+
+    /// An identifier for the request that can be used when contacting support.
+    pub request_id: Option<String>,
+
+    /// Links pointing to more information on handling the error.
+    pub help_links: Vec<HelpLink>,
+}
+
+/// The cause of an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorInfo {
+    /// A constant identifying the proximate cause of the error.
+    pub reason: String,
+
+    /// The logical grouping the reason belongs to, e.g. `firestore.googleapis.com`.
+    pub domain: String,
+
+    /// Additional structured details about the error.
+    pub metadata: HashMap<String, String>,
+}
+
+/// A single invalid field in a request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldViolation {
+    pub field: String,
+    pub description: String,
+}
+
+/// A single failed quota check.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuotaViolation {
+    pub subject: String,
+    pub description: String,
+}
+
+/// A single failed precondition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreconditionViolation {
+    pub kind: String,
+    pub subject: String,
+    pub description: String,
+}
+
+/// Describes the resource being accessed when an error occurred.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceInfo {
+    pub resource_type: String,
+    pub resource_name: String,
+    pub owner: String,
+    pub description: String,
+}
+
+/// A link to more information about an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HelpLink {
+    pub description: String,
+    pub url: String,
+}
+
+impl ErrorDetails {
+    pub(crate) fn from_status(status: &tonic::Status) -> Self {
+        let mut details = ErrorDetails {
+            message: status.message().to_string(),
+            ..ErrorDetails::default()
+        };
+
+        // The details bytes are an encoded google.rpc.Status, if present.
+        if status.details().is_empty() {
+            return details;
+        }
+
+        let decoded = match rpc::Status::decode(status.details()) {
+            Ok(decoded) => decoded,
+            Err(_) => return details,
+        };
+
+        if details.message.is_empty() {
+            details.message = decoded.message;
+        }
+
+        for any in decoded.details {
+            // Details we don't understand or can't decode are ignored - they're
+            // not essential to reporting the error.
+            details.add_detail(any).ok();
+        }
+
+        details
+    }
+
+    fn add_detail(&mut self, any: prost_types::Any) -> Result<(), prost::DecodeError> {
+        let type_name = any
+            .type_url
+            .strip_prefix(TYPE_URL_PREFIX)
+            .unwrap_or(&any.type_url);
+        let bytes = any.value.as_slice();
+
+        match type_name {
+            "google.rpc.ErrorInfo" => {
+                let info = rpc::ErrorInfo::decode(bytes)?;
+                self.error_info = Some(ErrorInfo {
+                    reason: info.reason,
+                    domain: info.domain,
+                    metadata: info.metadata,
+                });
+            }
+            "google.rpc.RetryInfo" => {
+                let info = rpc::RetryInfo::decode(bytes)?;
+                self.retry_delay = info.retry_delay.and_then(duration_from_proto);
+            }
+            "google.rpc.BadRequest" => {
+                let bad_request = rpc::BadRequest::decode(bytes)?;
+                self.field_violations
+                    .extend(
+                        bad_request
+                            .field_violations
+                            .into_iter()
+                            .map(|v| FieldViolation {
+                                field: v.field,
+                                description: v.description,
+                            }),
+                    );
+            }
+            "google.rpc.QuotaFailure" => {
+                let failure = rpc::QuotaFailure::decode(bytes)?;
+                self.quota_violations
+                    .extend(failure.violations.into_iter().map(|v| QuotaViolation {
+                        subject: v.subject,
+                        description: v.description,
+                    }));
+            }
+            "google.rpc.PreconditionFailure" => {
+                let failure = rpc::PreconditionFailure::decode(bytes)?;
+                self.precondition_violations
+                    .extend(
+                        failure
+                            .violations
+                            .into_iter()
+                            .map(|v| PreconditionViolation {
+                                kind: v.r#type,
+                                subject: v.subject,
+                                description: v.description,
+                            }),
+                    );
+            }
+            "google.rpc.ResourceInfo" => {
+                let info = rpc::ResourceInfo::decode(bytes)?;
+                self.resource_info = Some(ResourceInfo {
+                    resource_type: info.resource_type,
+                    resource_name: info.resource_name,
+                    owner: info.owner,
+                    description: info.description,
+                });
+            }
+            "google.rpc.RequestInfo" => {
+                let info = rpc::RequestInfo::decode(bytes)?;
+                self.request_id = Some(info.request_id);
+            }
+            "google.rpc.Help" => {
+                let help = rpc::Help::decode(bytes)?;
+                self.help_links
+                    .extend(help.links.into_iter().map(|l| HelpLink {
+                        description: l.description,
+                        url: l.url,
+                    }));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Finds the URL that can be used to create a missing index.
+    ///
+    /// Firestore includes this in the message of `FailedPrecondition` errors
+    /// for queries that need a composite index, and sometimes as a help link.
+    pub(crate) fn index_creation_url(&self) -> Option<&str> {
+        if let Some(link) = self
+            .help_links
+            .iter()
+            .find(|link| is_index_creation_url(&link.url))
+        {
+            return Some(&link.url);
+        }
+
+        self.message
+            .match_indices("https://")
+            .map(|(start, _)| {
+                let url = &self.message[start..];
+                let end = url.find(char::is_whitespace).unwrap_or(url.len());
+                &url[..end]
+            })
+            .find(|url| is_index_creation_url(url))
+    }
+}
+
+fn is_index_creation_url(url: &str) -> bool {
+    url.contains("/indexes") || url.contains("create_composite")
+}
+
+impl std::fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn duration_from_proto(duration: prost_types::Duration) -> Option<Duration> {
+    if duration.seconds < 0 || duration.nanos < 0 {
+        return None;
+    }
+
+    Some(Duration::new(
+        duration.seconds as u64,
+        duration.nanos as u32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn any<M: Message>(type_name: &str, message: M) -> prost_types::Any {
+        prost_types::Any {
+            type_url: format!("{}{}", TYPE_URL_PREFIX, type_name),
+            value: message.encode_to_vec(),
+        }
+    }
+
+    fn status_with_details(
+        code: tonic::Code,
+        message: &str,
+        details: Vec<prost_types::Any>,
+    ) -> tonic::Status {
+        let status = rpc::Status {
+            code: code as i32,
+            message: message.to_string(),
+            details,
+        };
+
+        tonic::Status::with_details(code, message, status.encode_to_vec().into())
+    }
+
+    #[test]
+    fn test_decoding_details() {
+        let status = status_with_details(
+            tonic::Code::ResourceExhausted,
+            "Quota exceeded",
+            vec![
+                any(
+                    "google.rpc.RetryInfo",
+                    rpc::RetryInfo {
+                        retry_delay: Some(prost_types::Duration {
+                            seconds: 2,
+                            nanos: 500,
+                        }),
+                    },
+                ),
+                any(
+                    "google.rpc.QuotaFailure",
+                    rpc::QuotaFailure {
+                        violations: vec![rpc::quota_failure::Violation {
+                            subject: "project:test".into(),
+                            description: "Daily limit exceeded".into(),
+                        }],
+                    },
+                ),
+                any(
+                    "google.rpc.BadRequest",
+                    rpc::BadRequest {
+                        field_violations: vec![rpc::bad_request::FieldViolation {
+                            field: "page_size".into(),
+                            description: "too big".into(),
+                        }],
+                    },
+                ),
+                any(
+                    "google.rpc.ErrorInfo",
+                    rpc::ErrorInfo {
+                        reason: "RATE_LIMIT_EXCEEDED".into(),
+                        domain: "firestore.googleapis.com".into(),
+                        metadata: HashMap::new(),
+                    },
+                ),
+                any("google.rpc.SomethingElse", rpc::DebugInfo::default()),
+            ],
+        );
+
+        insta::assert_debug_snapshot!(ErrorDetails::from_status(&status), @r###"
+        ErrorDetails {
+            message: "Quota exceeded",
+            error_info: Some(
+                ErrorInfo {
+                    reason: "RATE_LIMIT_EXCEEDED",
+                    domain: "firestore.googleapis.com",
+                    metadata: {},
+                },
+            ),
+            retry_delay: Some(
+                2.0000005s,
+            ),
+            field_violations: [
+                FieldViolation {
+                    field: "page_size",
+                    description: "too big",
+                },
+            ],
+            quota_violations: [
+                QuotaViolation {
+                    subject: "project:test",
+                    description: "Daily limit exceeded",
+                },
+            ],
+            precondition_violations: [],
+            resource_info: None,
+            request_id: None,
+            help_links: [],
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_missing_details() {
+        let status = tonic::Status::new(tonic::Code::Internal, "Something broke");
+
+        assert_eq!(
+            ErrorDetails::from_status(&status),
+            ErrorDetails {
+                message: "Something broke".into(),
+                ..ErrorDetails::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_index_creation_url() {
+        let status = tonic::Status::new(
+            tonic::Code::FailedPrecondition,
+            "The query requires an index. You can create it here: https://console.firebase.google.com/v1/r/project/test/firestore/indexes?create_composite=abc",
+        );
+
+        assert_eq!(
+            ErrorDetails::from_status(&status).index_creation_url(),
+            Some("https://console.firebase.google.com/v1/r/project/test/firestore/indexes?create_composite=abc")
+        );
+    }
+
+    #[test]
+    fn test_index_creation_url_ignores_other_urls() {
+        let status = tonic::Status::new(
+            tonic::Code::FailedPrecondition,
+            "Billing isn't enabled, see https://console.cloud.google.com/billing for details",
+        );
+
+        assert_eq!(
+            ErrorDetails::from_status(&status).index_creation_url(),
+            None
+        );
+
+        let status = tonic::Status::new(
+            tonic::Code::FailedPrecondition,
+            "See https://firebase.google.com/docs then create the index at https://console.firebase.google.com/v1/r/project/test/firestore/indexes?create_composite=abc",
+        );
+
+        assert_eq!(
+            ErrorDetails::from_status(&status).index_creation_url(),
+            Some("https://console.firebase.google.com/v1/r/project/test/firestore/indexes?create_composite=abc")
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Support for wrapping the gRPC service in user provided tower layers.
+//!
+//! Layers can produce any service type, so we box the result.  Most tower
+//! services are `Send` but not `Sync`, while `Database` needs to be `Sync`, so
+//! the boxed service is kept behind a mutex that's only ever locked to clone
+//! it.
+
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tonic::{
+    body::BoxBody,
+    codegen::{
+        http::{Request, Response},
+        StdError,
+    },
+};
+use tower::{util::BoxCloneService, Layer, ServiceExt};
+use tower_service::Service;
+
+/// The type of service that layers passed to `DatabaseBuilder::layer` wrap.
+pub type BoxService = BoxCloneService<Request<BoxBody>, Response<hyper::Body>, StdError>;
+
+/// Applies a layer to a `BoxService`, boxing the result.
+pub(super) type BoxLayer = Arc<dyn Fn(BoxService) -> BoxService + Send + Sync>;
+
+pub(super) fn box_layer<L>(layer: L) -> BoxLayer
+where
+    L: Layer<BoxService> + Send + Sync + 'static,
+    L::Service:
+        Service<Request<BoxBody>, Response = Response<hyper::Body>> + Clone + Send + 'static,
+    <L::Service as Service<Request<BoxBody>>>::Error: Into<StdError>,
+    <L::Service as Service<Request<BoxBody>>>::Future: Send + 'static,
+{
+    Arc::new(move |service| BoxCloneService::new(layer.layer(service).map_err(Into::into)))
+}
+
+pub struct LayeredService {
+    inner: Mutex<BoxService>,
+}
+
+impl LayeredService {
+    /// Wraps `service` in `layers`, with the first layer outermost.
+    pub fn new<S>(service: S, layers: &[BoxLayer]) -> Self
+    where
+        S: Service<Request<BoxBody>, Response = Response<hyper::Body>, Error = StdError>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let service = layers
+            .iter()
+            .rev()
+            .fold(BoxCloneService::new(service), |service, layer| {
+                layer(service)
+            });
+
+        LayeredService {
+            inner: Mutex::new(service),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut BoxService {
+        self.inner.get_mut().unwrap()
+    }
+}
+
+impl Clone for LayeredService {
+    fn clone(&self) -> Self {
+        LayeredService {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl Service<Request<BoxBody>> for LayeredService {
+    type Response = Response<hyper::Body>;
+    type Error = StdError;
+    type Future = <BoxService as Service<Request<BoxBody>>>::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        self.get_mut().call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::future::{ready, Ready};
+    use tower::layer::layer_fn;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct Empty;
+
+    impl Service<Request<BoxBody>> for Empty {
+        type Response = Response<hyper::Body>;
+        type Error = StdError;
+        type Future = Ready<Result<Self::Response, StdError>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), StdError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<BoxBody>) -> Self::Future {
+            ready(Ok(Response::new(hyper::Body::empty())))
+        }
+    }
+
+    #[derive(Clone)]
+    struct Record<S> {
+        inner: S,
+        name: &'static str,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl<S> Service<Request<BoxBody>> for Record<S>
+    where
+        S: Service<Request<BoxBody>>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+            self.log.lock().unwrap().push(self.name);
+            self.inner.call(request)
+        }
+    }
+
+    fn record(name: &'static str, log: &Arc<Mutex<Vec<&'static str>>>) -> BoxLayer {
+        let log = log.clone();
+        box_layer(layer_fn(move |inner| Record {
+            inner,
+            name,
+            log: log.clone(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_layers_apply_outermost_first() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let service = LayeredService::new(Empty, &[record("outer", &log), record("inner", &log)]);
+
+        service
+            .clone()
+            .oneshot(Request::new(tonic::body::empty_body()))
+            .await
+            .unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec!["outer", "inner"]);
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_layer_state() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let layer = box_layer(layer_fn(move |inner: BoxService| {
+            let counter = counter.clone();
+            inner.map_request(move |request| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                request
+            })
+        }));
+        let service = LayeredService::new(Empty, &[layer]);
+
+        for _ in 0..3 {
+            service
+                .clone()
+                .oneshot(Request::new(tonic::body::empty_body()))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
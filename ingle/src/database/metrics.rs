@@ -0,0 +1,233 @@
+//! Hooks for recording metrics about the requests a `Database` makes.
+
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+
+use super::FirestoreError;
+use crate::google::firestore::v1::{self as firestore, write::Operation};
+
+/// Receives metrics about the requests made by a `Database`.
+///
+/// Implement this to forward metrics to e.g. a Prometheus registry, or
+/// enable the `metrics` feature to use `MetricsFacade`.  Collections are
+/// identified by their ID rather than their full path to keep cardinality
+/// down.  Every method does nothing by default.
+#[allow(unused_variables)]
+pub trait MetricsRecorder: Send + Sync {
+    /// Called after every RPC attempt with the gRPC method, status code (e.g.
+    /// `NOT_FOUND`) and how long the attempt took.
+    fn rpc_completed(&self, method: &'static str, code: &'static str, latency: Duration) {}
+
+    /// Called before an RPC is retried.
+    fn rpc_retried(&self, method: &'static str) {}
+
+    /// Called with the number of documents each successful read returned.
+    fn documents_read(&self, collection: &str, count: u64) {}
+
+    /// Called with the number of documents each successful write or commit
+    /// wrote to a collection.
+    fn documents_written(&self, collection: &str, count: u64) {}
+
+    /// Called with the number of documents each successful commit deleted
+    /// from a collection.
+    fn documents_deleted(&self, collection: &str, count: u64) {}
+
+    /// Called before a transaction is retried, with the code of the error that
+    /// caused the retry.
+    fn transaction_retried(&self, code: &'static str) {}
+}
+
+/// The `MetricsRecorder` for a `Database`, if it has one.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics {
+    recorder: Option<Arc<dyn MetricsRecorder>>,
+}
+
+impl Metrics {
+    pub fn new(recorder: Arc<dyn MetricsRecorder>) -> Self {
+        Metrics {
+            recorder: Some(recorder),
+        }
+    }
+
+    /// Runs a single RPC attempt, recording its latency.
+    pub async fn observe<F, T>(&self, method: &'static str, rpc: F) -> Result<T, FirestoreError>
+    where
+        F: Future<Output = Result<T, FirestoreError>>,
+    {
+        let recorder = match &self.recorder {
+            Some(recorder) => recorder,
+            None => return rpc.await,
+        };
+
+        let started_at = Instant::now();
+        let result = rpc.await;
+        let code = match &result {
+            Ok(_) => "OK",
+            Err(error) => error.code_name(),
+        };
+        recorder.rpc_completed(method, code, started_at.elapsed());
+
+        result
+    }
+
+    /// Records an attempt at an RPC, where `attempt` starts at 1.
+    pub fn attempt(&self, method: &'static str, attempt: u32) {
+        if let (Some(recorder), true) = (&self.recorder, attempt > 1) {
+            recorder.rpc_retried(method);
+        }
+    }
+
+    pub fn documents_read(&self, collection: &str, count: usize) {
+        if let Some(recorder) = &self.recorder {
+            recorder.documents_read(collection, count as u64);
+        }
+    }
+
+    pub fn documents_written(&self, collection: &str, count: usize) {
+        if let Some(recorder) = &self.recorder {
+            recorder.documents_written(collection, count as u64);
+        }
+    }
+
+    pub fn documents_committed(&self, counts: CommitCounts) {
+        if let Some(recorder) = &self.recorder {
+            for (collection, count) in counts.written {
+                recorder.documents_written(&collection, count as u64);
+            }
+            for (collection, count) in counts.deleted {
+                recorder.documents_deleted(&collection, count as u64);
+            }
+        }
+    }
+
+    pub fn transaction_retried(&self, code: &'static str) {
+        if let Some(recorder) = &self.recorder {
+            recorder.transaction_retried(code);
+        }
+    }
+}
+
+/// The number of documents a commit writes & deletes in each collection.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CommitCounts {
+    pub written: BTreeMap<String, usize>,
+    pub deleted: BTreeMap<String, usize>,
+}
+
+impl CommitCounts {
+    pub fn new(writes: &[firestore::Write]) -> Self {
+        let mut counts = CommitCounts::default();
+        for write in writes {
+            let (counts, name) = match &write.operation {
+                Some(Operation::Update(document)) => (&mut counts.written, &document.name),
+                Some(Operation::Delete(name)) => (&mut counts.deleted, name),
+                Some(Operation::Transform(transform)) => (&mut counts.written, &transform.document),
+                None => continue,
+            };
+            *counts.entry(collection_id(name).to_string()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// A `MetricsRecorder` that reports to the global recorder of the `metrics`
+/// crate.
+///
+/// This records:
+///
+/// - `ingle_rpcs_total` & `ingle_rpc_duration_seconds`, labelled with
+///   `method` & `code`.
+/// - `ingle_rpc_retries_total`, labelled with `method`.
+/// - `ingle_documents_read_total`, `ingle_documents_written_total` &
+///   `ingle_documents_deleted_total`, labelled with `collection`.
+/// - `ingle_transaction_retries_total`, labelled with `code`.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder for MetricsFacade {
+    fn rpc_completed(&self, method: &'static str, code: &'static str, latency: Duration) {
+        let labels = [("method", method), ("code", code)];
+        ::metrics::counter!("ingle_rpcs_total", 1, &labels);
+        ::metrics::histogram!("ingle_rpc_duration_seconds", latency, &labels);
+    }
+
+    fn rpc_retried(&self, method: &'static str) {
+        ::metrics::counter!("ingle_rpc_retries_total", 1, "method" => method);
+    }
+
+    fn documents_read(&self, collection: &str, count: u64) {
+        let collection = collection.to_string();
+        ::metrics::counter!("ingle_documents_read_total", count, "collection" => collection);
+    }
+
+    fn documents_written(&self, collection: &str, count: u64) {
+        let collection = collection.to_string();
+        ::metrics::counter!("ingle_documents_written_total", count, "collection" => collection);
+    }
+
+    fn documents_deleted(&self, collection: &str, count: u64) {
+        let collection = collection.to_string();
+        ::metrics::counter!("ingle_documents_deleted_total", count, "collection" => collection);
+    }
+
+    fn transaction_retried(&self, code: &'static str) {
+        ::metrics::counter!("ingle_transaction_retries_total", 1, "code" => code);
+    }
+}
+
+/// The ID of the collection containing a document, given its full name.
+pub(crate) fn collection_id(document_name: &str) -> &str {
+    document_name.rsplit('/').nth(1).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_id() {
+        assert_eq!(
+            collection_id("projects/p/databases/(default)/documents/books/a"),
+            "books"
+        );
+        assert_eq!(
+            collection_id("projects/p/databases/(default)/documents/books/a/reviews/b"),
+            "reviews"
+        );
+        assert_eq!(collection_id("/books/a"), "books");
+    }
+
+    #[test]
+    fn test_commit_counts() {
+        let update = |name: &str| firestore::Write {
+            operation: Some(Operation::Update(firestore::Document {
+                name: name.to_string(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let delete = |name: &str| firestore::Write {
+            operation: Some(Operation::Delete(name.to_string())),
+            ..Default::default()
+        };
+
+        let counts = CommitCounts::new(&[
+            update("/books/a"),
+            update("/books/b"),
+            update("/authors/a"),
+            delete("/books/c"),
+        ]);
+
+        assert_eq!(
+            counts,
+            CommitCounts {
+                written: [("authors".to_string(), 1), ("books".to_string(), 2)].into(),
+                deleted: [("books".to_string(), 1)].into(),
+            }
+        );
+    }
+}
@@ -1,5 +1,6 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
-use tonic::transport::Channel;
 
 use crate::{
     document::DocumentResponse,
@@ -10,20 +11,40 @@ use crate::{
     values::{DecodingError, DocumentValues},
 };
 
-mod auth;
+pub(crate) mod auth;
 mod builder;
+pub mod error_details;
+mod layer;
+mod metrics;
+mod pool;
+mod rest;
+mod retry;
+mod routing;
+mod tls;
+mod trace;
 pub mod transactions;
 
-pub use builder::{ConnectError, DatabaseBuilder};
+pub use builder::{ConnectError, DatabaseBuilder, DefaultCredentialsError, FromEnvError};
+pub use error_details::ErrorDetails;
+pub use layer::BoxService;
+#[cfg(feature = "metrics")]
+pub use metrics::MetricsFacade;
+pub use metrics::MetricsRecorder;
+pub use pool::Balancing;
+pub use rest::Transport;
+pub use retry::RetryPolicy;
+pub use tls::TlsConfig;
 
-use self::auth::AuthService;
+use self::{layer::LayeredService, metrics::Metrics};
 
-type FirestoreClient = firestore::firestore_client::FirestoreClient<AuthService<Channel>>;
+type FirestoreClient = firestore::firestore_client::FirestoreClient<LayeredService>;
 
 #[derive(Clone)]
 pub struct Database {
     client: FirestoreClient,
     project_path: ProjectPath,
+    retry_policy: RetryPolicy,
+    metrics: Metrics,
 }
 
 #[async_trait]
@@ -32,12 +53,36 @@ impl ReadExecutor for Database {
         &self,
         input: operations::ListDocumentsRequest,
     ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
-        let mut client = self.client.clone();
+        let span = trace::Span::rpc("ListDocuments", &self.project_path);
+        span.record_collection(input.collection_path());
+        span.record_page_size(input.page_size());
+
+        let collection_id = input.collection_path().id().to_string();
+        let retry_policy = input.retry_policy().unwrap_or(&self.retry_policy).clone();
+        let request = input.into_firestore_request(self.project_path.clone());
+
+        let mut attempt = 0;
+        let response = span
+            .instrument(retry_policy.retry(|| {
+                attempt += 1;
+                self.metrics.attempt("ListDocuments", attempt);
 
-        let response = client
-            .list_documents(input.into_firestore_request(self.project_path.clone()))
-            .await?
-            .into_inner();
+                let mut client = self.client.clone();
+                let request = request.clone();
+                self.metrics.observe("ListDocuments", async move {
+                    client
+                        .list_documents(request)
+                        .await
+                        .map_err(FirestoreError::from)
+                })
+            }))
+            .await;
+
+        span.record_result(&response);
+        let response = response?.into_inner();
+        span.record_documents(response.documents.len());
+        self.metrics
+            .documents_read(&collection_id, response.documents.len());
 
         let next_page_token = if response.next_page_token.is_empty() {
             None
@@ -62,98 +107,191 @@ impl WriteExecutor for Database {
         &self,
         input: operations::AddDocumentRequest,
     ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let span = trace::Span::rpc("CreateDocument", &self.project_path);
+        span.record_collection(input.collection_path());
+
+        let collection_id = input.collection_path().id().to_string();
+        let request = input.into_firestore_request(self.project_path.clone());
+
         let mut client = self.client.clone();
+        let response = span
+            .instrument(self.metrics.observe("CreateDocument", async {
+                client
+                    .create_document(request)
+                    .await
+                    .map_err(FirestoreError::from)
+            }))
+            .await;
 
-        Ok(client
-            .create_document(input.into_firestore_request(self.project_path.clone()))
-            .await?
-            .into_inner()
-            .try_into_document_response()?)
+        span.record_result(&response);
+        let response = response?;
+        self.metrics.documents_written(&collection_id, 1);
+
+        Ok(response.into_inner().try_into_document_response()?)
     }
 
     async fn set_document(
         &self,
         input: operations::SetDocumentRequest,
     ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
-        let mut client = self.client.clone();
+        let span = trace::Span::rpc("UpdateDocument", &self.project_path);
+        span.record_document(input.document_path());
+
+        let collection_id =
+            metrics::collection_id(input.document_path().relative_path()).to_string();
+        let retry_policy = input.retry_policy().unwrap_or(&self.retry_policy).clone();
+        let request = input.into_firestore_request(self.project_path.clone());
+
+        let mut attempt = 0;
+        let response = span
+            .instrument(retry_policy.retry(|| {
+                attempt += 1;
+                self.metrics.attempt("UpdateDocument", attempt);
+
+                let mut client = self.client.clone();
+                let request = request.clone();
+                self.metrics.observe("UpdateDocument", async move {
+                    client
+                        .update_document(request)
+                        .await
+                        .map_err(FirestoreError::from)
+                })
+            }))
+            .await;
+
+        span.record_result(&response);
+        let response = response?;
+        self.metrics.documents_written(&collection_id, 1);
 
-        Ok(client
-            .update_document(input.into_firestore_request(self.project_path.clone()))
-            .await?
-            .into_inner()
-            .try_into_document_response()?)
+        Ok(response.into_inner().try_into_document_response()?)
     }
 }
 
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum FirestoreError {
-    #[error("unknown error")]
-    UnknownError,
-    #[error("operation was cancelled")]
-    Cancelled,
+    #[error("unknown error: {0}")]
+    UnknownError(Box<ErrorDetails>),
+    #[error("operation was cancelled: {0}")]
+    Cancelled(Box<ErrorDetails>),
     #[error("invalid argument: {0}")]
-    InvalidArgument(String),
-    #[error("deadling exceeded")]
-    DeadlineExceeded,
-    #[error("not found")]
-    NotFound,
-    #[error("already exists")]
-    AlreadyExists,
+    InvalidArgument(Box<ErrorDetails>),
+    #[error("deadline exceeded: {0}")]
+    DeadlineExceeded(Box<ErrorDetails>),
+    #[error("not found: {0}")]
+    NotFound(Box<ErrorDetails>),
+    #[error("already exists: {0}")]
+    AlreadyExists(Box<ErrorDetails>),
     #[error("permission denied: {0}")]
-    PermissionDenied(String),
+    PermissionDenied(Box<ErrorDetails>),
     #[error("resource exhausted: {0}")]
-    ResourceExhausted(String),
+    ResourceExhausted(Box<ErrorDetails>),
     #[error("failed precondition: {0}")]
-    FailedPrecondition(String),
+    FailedPrecondition(Box<ErrorDetails>),
     #[error("aborted: {0}")]
-    Aborted(String),
+    Aborted(Box<ErrorDetails>),
     #[error("out of range: {0}")]
-    OutOfRange(String),
-    #[error("unimplemented")]
-    Unimplemented,
-    #[error("internal error. try again")]
-    Internal,
-    #[error("service unavailable. try again")]
-    Unavailable,
-    #[error("unrecoverable data loss or corruption")]
-    DataLoss,
+    OutOfRange(Box<ErrorDetails>),
+    #[error("unimplemented: {0}")]
+    Unimplemented(Box<ErrorDetails>),
+    #[error("internal error: {0}. try again")]
+    Internal(Box<ErrorDetails>),
+    #[error("service unavailable: {0}. try again")]
+    Unavailable(Box<ErrorDetails>),
+    #[error("unrecoverable data loss or corruption: {0}")]
+    DataLoss(Box<ErrorDetails>),
     #[error("unauthenticated: {0}")]
-    Unauthenticated(String),
+    Unauthenticated(Box<ErrorDetails>),
     #[error("Malformed response: {0}")]
     MalformedResponse(#[from] DecodingError),
 }
 
+impl FirestoreError {
+    /// The message & structured details returned from Firestore with this error.
+    ///
+    /// This is `None` for errors that didn't come from Firestore itself.
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            FirestoreError::UnknownError(details)
+            | FirestoreError::Cancelled(details)
+            | FirestoreError::InvalidArgument(details)
+            | FirestoreError::DeadlineExceeded(details)
+            | FirestoreError::NotFound(details)
+            | FirestoreError::AlreadyExists(details)
+            | FirestoreError::PermissionDenied(details)
+            | FirestoreError::ResourceExhausted(details)
+            | FirestoreError::FailedPrecondition(details)
+            | FirestoreError::Aborted(details)
+            | FirestoreError::OutOfRange(details)
+            | FirestoreError::Unimplemented(details)
+            | FirestoreError::Internal(details)
+            | FirestoreError::Unavailable(details)
+            | FirestoreError::DataLoss(details)
+            | FirestoreError::Unauthenticated(details) => Some(details.as_ref()),
+            FirestoreError::MalformedResponse(_) => None,
+        }
+    }
+
+    /// How long Firestore asked us to wait before retrying, if it did.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.details()?.retry_delay
+    }
+
+    /// The URL that can be used to create the index a query needs.
+    ///
+    /// Only present on `FailedPrecondition` errors caused by a missing index.
+    pub fn index_creation_url(&self) -> Option<&str> {
+        match self {
+            FirestoreError::FailedPrecondition(details) => details.index_creation_url(),
+            _ => None,
+        }
+    }
+
+    /// The gRPC name for this error's code, e.g. `NOT_FOUND`.
+    pub(crate) fn code_name(&self) -> &'static str {
+        match self {
+            FirestoreError::UnknownError(_) => "UNKNOWN",
+            FirestoreError::Cancelled(_) => "CANCELLED",
+            FirestoreError::InvalidArgument(_) => "INVALID_ARGUMENT",
+            FirestoreError::DeadlineExceeded(_) => "DEADLINE_EXCEEDED",
+            FirestoreError::NotFound(_) => "NOT_FOUND",
+            FirestoreError::AlreadyExists(_) => "ALREADY_EXISTS",
+            FirestoreError::PermissionDenied(_) => "PERMISSION_DENIED",
+            FirestoreError::ResourceExhausted(_) => "RESOURCE_EXHAUSTED",
+            FirestoreError::FailedPrecondition(_) => "FAILED_PRECONDITION",
+            FirestoreError::Aborted(_) => "ABORTED",
+            FirestoreError::OutOfRange(_) => "OUT_OF_RANGE",
+            FirestoreError::Unimplemented(_) => "UNIMPLEMENTED",
+            FirestoreError::Internal(_) => "INTERNAL",
+            FirestoreError::Unavailable(_) => "UNAVAILABLE",
+            FirestoreError::DataLoss(_) => "DATA_LOSS",
+            FirestoreError::Unauthenticated(_) => "UNAUTHENTICATED",
+            FirestoreError::MalformedResponse(_) => "MALFORMED_RESPONSE",
+        }
+    }
+}
+
 impl From<tonic::Status> for FirestoreError {
     fn from(status: tonic::Status) -> Self {
+        let details = Box::new(ErrorDetails::from_status(&status));
+
         match status.code() {
-            tonic::Code::Ok => FirestoreError::UnknownError,
-            tonic::Code::Cancelled => FirestoreError::Cancelled,
-            tonic::Code::Unknown => FirestoreError::UnknownError,
-            tonic::Code::InvalidArgument => {
-                // See https://github.com/googleapis/googleapis/blob/master/google/rpc/error_details.proto for details
-                FirestoreError::InvalidArgument(status.message().to_string())
-            }
-            tonic::Code::DeadlineExceeded => FirestoreError::DeadlineExceeded,
-            tonic::Code::NotFound => FirestoreError::NotFound,
-            tonic::Code::AlreadyExists => FirestoreError::AlreadyExists,
-            tonic::Code::PermissionDenied => {
-                FirestoreError::PermissionDenied(status.message().to_string())
-            }
-            tonic::Code::ResourceExhausted => {
-                FirestoreError::ResourceExhausted(status.message().to_string())
-            }
-            tonic::Code::FailedPrecondition => {
-                FirestoreError::FailedPrecondition(status.message().to_string())
-            }
-            tonic::Code::Aborted => FirestoreError::Aborted(status.message().to_string()),
-            tonic::Code::OutOfRange => FirestoreError::OutOfRange(status.message().to_string()),
-            tonic::Code::Unimplemented => FirestoreError::Unimplemented,
-            tonic::Code::Internal => FirestoreError::Internal,
-            tonic::Code::Unavailable => FirestoreError::Unavailable,
-            tonic::Code::DataLoss => FirestoreError::DataLoss,
-            tonic::Code::Unauthenticated => {
-                FirestoreError::Unauthenticated(status.message().to_string())
-            }
+            tonic::Code::Ok => FirestoreError::UnknownError(details),
+            tonic::Code::Cancelled => FirestoreError::Cancelled(details),
+            tonic::Code::Unknown => FirestoreError::UnknownError(details),
+            tonic::Code::InvalidArgument => FirestoreError::InvalidArgument(details),
+            tonic::Code::DeadlineExceeded => FirestoreError::DeadlineExceeded(details),
+            tonic::Code::NotFound => FirestoreError::NotFound(details),
+            tonic::Code::AlreadyExists => FirestoreError::AlreadyExists(details),
+            tonic::Code::PermissionDenied => FirestoreError::PermissionDenied(details),
+            tonic::Code::ResourceExhausted => FirestoreError::ResourceExhausted(details),
+            tonic::Code::FailedPrecondition => FirestoreError::FailedPrecondition(details),
+            tonic::Code::Aborted => FirestoreError::Aborted(details),
+            tonic::Code::OutOfRange => FirestoreError::OutOfRange(details),
+            tonic::Code::Unimplemented => FirestoreError::Unimplemented(details),
+            tonic::Code::Internal => FirestoreError::Internal(details),
+            tonic::Code::Unavailable => FirestoreError::Unavailable(details),
+            tonic::Code::DataLoss => FirestoreError::DataLoss(details),
+            tonic::Code::Unauthenticated => FirestoreError::Unauthenticated(details),
         }
     }
 }
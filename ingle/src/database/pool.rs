@@ -0,0 +1,175 @@
+//! A pool of gRPC channels.
+//!
+//! Each tonic `Channel` is a single HTTP/2 connection, which limits how many
+//! concurrent streams we can have open.  This spreads requests across several.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures_util::future::{poll_fn, BoxFuture};
+use tower_service::Service;
+
+/// How requests are spread across the channels in a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balancing {
+    /// Each request goes to the next channel in turn.
+    RoundRobin,
+    /// Each request goes to the channel with the fewest requests in flight.
+    LeastLoaded,
+}
+
+#[derive(Clone)]
+pub struct ChannelPool<S> {
+    channels: Arc<[PooledChannel<S>]>,
+    balancing: Balancing,
+    next: Arc<AtomicUsize>,
+}
+
+struct PooledChannel<S> {
+    channel: S,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl<S> ChannelPool<S> {
+    /// Creates a pool from a non-empty list of channels.
+    pub fn new(channels: Vec<S>, balancing: Balancing) -> Self {
+        assert!(
+            !channels.is_empty(),
+            "ChannelPool needs at least one channel"
+        );
+
+        ChannelPool {
+            channels: channels
+                .into_iter()
+                .map(|channel| PooledChannel {
+                    channel,
+                    in_flight: Arc::new(AtomicUsize::new(0)),
+                })
+                .collect(),
+            balancing,
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn pick(&self) -> &PooledChannel<S> {
+        let index = match self.balancing {
+            Balancing::RoundRobin => {
+                self.next.fetch_add(1, Ordering::Relaxed) % self.channels.len()
+            }
+            Balancing::LeastLoaded => {
+                // Start from a rotating offset so ties don't all go to the first channel.
+                let offset = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..self.channels.len())
+                    .map(|i| (offset + i) % self.channels.len())
+                    .min_by_key(|&i| self.channels[i].in_flight.load(Ordering::Relaxed))
+                    .unwrap_or(0)
+            }
+        };
+
+        &self.channels[index]
+    }
+}
+
+impl<S, Request> Service<Request> for ChannelPool<S>
+where
+    S: Service<Request> + Clone + Send + 'static,
+    S::Future: Send,
+    Request: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness is checked on whichever channel we pick in `call`.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let pooled = self.pick();
+        let mut channel = pooled.channel.clone();
+        let in_flight = InFlight::new(&pooled.in_flight);
+
+        Box::pin(async move {
+            poll_fn(|cx| channel.poll_ready(cx)).await?;
+            let response = channel.call(request).await;
+            drop(in_flight);
+            response
+        })
+    }
+}
+
+/// Counts a request as in flight until dropped.
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn new(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        InFlight(Arc::clone(counter))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{ready, Ready};
+
+    use super::*;
+
+    /// A service that responds with its own ID.
+    #[derive(Clone)]
+    struct Id(usize);
+
+    impl Service<()> for Id {
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: ()) -> Self::Future {
+            ready(Ok(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let mut pool = ChannelPool::new(vec![Id(0), Id(1), Id(2)], Balancing::RoundRobin);
+
+        let mut ids = vec![];
+        for _ in 0..6 {
+            ids.push(pool.call(()).await.unwrap());
+        }
+
+        assert_eq!(ids, vec![0, 1, 2, 0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_least_loaded() {
+        let mut pool = ChannelPool::new(vec![Id(0), Id(1), Id(2)], Balancing::LeastLoaded);
+
+        // Requests are in flight from when they're called until they complete,
+        // so this keeps the first channel loaded.
+        let in_flight = pool.call(());
+
+        let mut ids = vec![];
+        for _ in 0..3 {
+            ids.push(pool.call(()).await.unwrap());
+        }
+
+        assert_eq!(ids, vec![1, 2, 1]);
+        assert_eq!(in_flight.await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,1180 @@
+//! A transport that talks to Firestore's REST API rather than gRPC.
+//!
+//! `RestService` sits where the gRPC channels would.  It decodes each request
+//! tonic sends, makes the equivalent call to the `v1` JSON API over HTTP/1.1
+//! and encodes the response back into a gRPC message, so retries,
+//! transactions, auth & layers all work the same on either transport.
+
+// The errors here become the responses of the gRPC service, so are tonic's Status
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::future::BoxFuture;
+use hyper::{client::HttpConnector, header, Body, Client, Method, StatusCode, Uri};
+use hyper_rustls::HttpsConnector;
+use prost::{
+    bytes::{Buf, Bytes},
+    Message,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as Json};
+use tonic::{
+    body::BoxBody,
+    codegen::{
+        http::{HeaderMap, Request, Response},
+        StdError,
+    },
+    Code, Status,
+};
+use tower_service::Service;
+
+use crate::{
+    google::{firestore::v1 as firestore, rpc},
+    json::{self, JsonError},
+};
+
+static RPC_PREFIX: &str = "/google.firestore.v1.Firestore/";
+static TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+/// How a `Database` talks to Firestore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    /// gRPC over HTTP/2.  This is the default.
+    Grpc,
+    /// The JSON REST API over HTTP/1.1, for networks that only allow HTTP/1.1.
+    ///
+    /// Every operation works the same as over gRPC, but `pool_size` &
+    /// `balancing` are ignored and `TlsConfig::domain_name` isn't supported.
+    Rest,
+}
+
+// Derived enum defaults need Rust 1.62.
+#[allow(clippy::derivable_impls)]
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Grpc
+    }
+}
+
+#[derive(Clone)]
+pub struct RestService {
+    client: Client<HttpsConnector<HttpConnector>>,
+    endpoint: String,
+    timeout: Option<Duration>,
+}
+
+impl RestService {
+    pub fn new(
+        endpoint: &Uri,
+        tls: rustls::ClientConfig,
+        tcp_keepalive: Option<Duration>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_keepalive(tcp_keepalive);
+
+        RestService {
+            client: Client::builder().build((http, tls).into()),
+            endpoint: endpoint.to_string().trim_end_matches('/').to_string(),
+            timeout,
+        }
+    }
+
+    async fn send(self, request: Request<BoxBody>) -> Result<Response<Body>, Status> {
+        let rpc = Rpc::from_path(request.uri().path())?;
+        let (parts, body) = request.into_parts();
+        let rest_request = rpc.request_to_rest(read_message(body).await?)?;
+
+        let mut http_request = hyper::Request::builder()
+            .method(rest_request.method)
+            .uri(format!("{}/v1/{}", self.endpoint, rest_request.path))
+            .header(header::CONTENT_TYPE, "application/json");
+        for (name, value) in forwarded_headers(&parts.headers) {
+            http_request = http_request.header(name, value);
+        }
+        let http_request = http_request
+            .body(match rest_request.body {
+                Some(body) => Body::from(body.to_string()),
+                None => Body::empty(),
+            })
+            .map_err(|e| Status::internal(format!("Couldn't build REST request: {}", e)))?;
+
+        let client = self.client;
+        let response = async move {
+            let response = client.request(http_request).await?;
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            Ok::<_, hyper::Error>((status, body))
+        };
+        let (status, body) = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response)
+                .await
+                .map_err(|_| Status::deadline_exceeded("REST request timed out"))?,
+            None => response.await,
+        }
+        .map_err(|e| Status::unavailable(format!("REST request failed: {}", e)))?;
+
+        if !status.is_success() {
+            return Err(error_status(status, &body));
+        }
+
+        let json = serde_json::from_slice(&body)
+            .map_err(|e| Status::internal(format!("Invalid JSON in REST response: {}", e)))?;
+        rpc.response_from_rest(json).map_err(|e| {
+            Status::internal(format!("Couldn't decode {:?} REST response: {}", rpc, e))
+        })
+    }
+}
+
+impl Service<Request<BoxBody>> for RestService {
+    type Response = Response<Body>;
+    type Error = StdError;
+    type Future = BoxFuture<'static, Result<Response<Body>, StdError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        let service = self.clone();
+
+        // tonic recognises a boxed Status and returns it as is.
+        Box::pin(async move { service.send(request).await.map_err(Into::into) })
+    }
+}
+
+/// The Firestore RPCs that ingle makes.
+#[derive(Clone, Copy, Debug)]
+enum Rpc {
+    ListDocuments,
+    CreateDocument,
+    UpdateDocument,
+    BeginTransaction,
+    Commit,
+    Rollback,
+}
+
+struct RestRequest {
+    method: Method,
+    path: String,
+    body: Option<Json>,
+}
+
+impl RestRequest {
+    fn new(method: Method, path: &str, query: Query) -> Self {
+        let mut path = encode_path(path);
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query.0)
+            .finish();
+        if !query.is_empty() {
+            path.push('?');
+            path.push_str(&query);
+        }
+
+        RestRequest {
+            method,
+            path,
+            body: None,
+        }
+    }
+
+    fn body(self, body: Json) -> Self {
+        RestRequest {
+            body: Some(body),
+            ..self
+        }
+    }
+}
+
+impl Rpc {
+    fn from_path(path: &str) -> Result<Rpc, Status> {
+        Ok(match path.strip_prefix(RPC_PREFIX) {
+            Some("ListDocuments") => Rpc::ListDocuments,
+            Some("CreateDocument") => Rpc::CreateDocument,
+            Some("UpdateDocument") => Rpc::UpdateDocument,
+            Some("BeginTransaction") => Rpc::BeginTransaction,
+            Some("Commit") => Rpc::Commit,
+            Some("Rollback") => Rpc::Rollback,
+            _ => {
+                return Err(Status::unimplemented(format!(
+                    "{} isn't supported by the REST transport",
+                    path
+                )))
+            }
+        })
+    }
+
+    /// Converts an encoded gRPC request into the equivalent REST request.
+    fn request_to_rest(self, message: Bytes) -> Result<RestRequest, Status> {
+        let decode_error =
+            |e: prost::DecodeError| Status::internal(format!("Couldn't decode request: {}", e));
+
+        Ok(match self {
+            Rpc::ListDocuments => {
+                use firestore::list_documents_request::ConsistencySelector;
+
+                let request =
+                    firestore::ListDocumentsRequest::decode(message).map_err(decode_error)?;
+                let mut query = Query::default();
+                if request.page_size != 0 {
+                    query.append("pageSize", &request.page_size.to_string());
+                }
+                query.append_non_empty("pageToken", &request.page_token);
+                query.append_non_empty("orderBy", &request.order_by);
+                query.append_mask("mask", request.mask.as_ref());
+                if request.show_missing {
+                    query.append("showMissing", "true");
+                }
+                match &request.consistency_selector {
+                    Some(ConsistencySelector::Transaction(transaction)) => {
+                        query.append("transaction", &json::bytes_to_json(transaction))
+                    }
+                    Some(ConsistencySelector::ReadTime(time)) => {
+                        query.append("readTime", &json::timestamp_to_json(time))
+                    }
+                    None => {}
+                }
+
+                RestRequest::new(
+                    Method::GET,
+                    &format!("{}/{}", request.parent, request.collection_id),
+                    query,
+                )
+            }
+            Rpc::CreateDocument => {
+                let request =
+                    firestore::CreateDocumentRequest::decode(message).map_err(decode_error)?;
+                let mut query = Query::default();
+                query.append_non_empty("documentId", &request.document_id);
+                query.append_mask("mask", request.mask.as_ref());
+
+                RestRequest::new(
+                    Method::POST,
+                    &format!("{}/{}", request.parent, request.collection_id),
+                    query,
+                )
+                .body(json::document_to_json(
+                    &request.document.unwrap_or_default(),
+                ))
+            }
+            Rpc::UpdateDocument => {
+                use firestore::precondition::ConditionType;
+
+                let request =
+                    firestore::UpdateDocumentRequest::decode(message).map_err(decode_error)?;
+                let document = request.document.unwrap_or_default();
+                let mut query = Query::default();
+                query.append_mask("updateMask", request.update_mask.as_ref());
+                query.append_mask("mask", request.mask.as_ref());
+                match request.current_document.and_then(|p| p.condition_type) {
+                    Some(ConditionType::Exists(exists)) => {
+                        query.append("currentDocument.exists", &exists.to_string())
+                    }
+                    Some(ConditionType::UpdateTime(time)) => query.append(
+                        "currentDocument.updateTime",
+                        &json::timestamp_to_json(&time),
+                    ),
+                    None => {}
+                }
+
+                RestRequest::new(Method::PATCH, &document.name, query)
+                    .body(json::document_to_json(&document))
+            }
+            Rpc::BeginTransaction => {
+                let request =
+                    firestore::BeginTransactionRequest::decode(message).map_err(decode_error)?;
+                let mut body = Map::new();
+                if let Some(options) = &request.options {
+                    body.insert("options".into(), transaction_options_to_json(options));
+                }
+
+                RestRequest::new(
+                    Method::POST,
+                    &format!("{}/documents:beginTransaction", request.database),
+                    Query::default(),
+                )
+                .body(Json::Object(body))
+            }
+            Rpc::Commit => {
+                let request = firestore::CommitRequest::decode(message).map_err(decode_error)?;
+                let mut body = Map::new();
+                body.insert(
+                    "writes".into(),
+                    request
+                        .writes
+                        .iter()
+                        .map(write_to_json)
+                        .collect::<Result<_, _>>()?,
+                );
+                if !request.transaction.is_empty() {
+                    body.insert(
+                        "transaction".into(),
+                        json::bytes_to_json(&request.transaction).into(),
+                    );
+                }
+
+                RestRequest::new(
+                    Method::POST,
+                    &format!("{}/documents:commit", request.database),
+                    Query::default(),
+                )
+                .body(Json::Object(body))
+            }
+            Rpc::Rollback => {
+                let request = firestore::RollbackRequest::decode(message).map_err(decode_error)?;
+
+                RestRequest::new(
+                    Method::POST,
+                    &format!("{}/documents:rollback", request.database),
+                    Query::default(),
+                )
+                .body(json!({ "transaction": json::bytes_to_json(&request.transaction) }))
+            }
+        })
+    }
+
+    /// Converts a REST response into the equivalent gRPC response.
+    fn response_from_rest(self, json: Json) -> Result<Response<Body>, JsonError> {
+        Ok(match self {
+            Rpc::ListDocuments => {
+                let mut response = json::expect_object(json, "response")?;
+                grpc_response(firestore::ListDocumentsResponse {
+                    documents: take_array(&mut response, "documents")?
+                        .into_iter()
+                        .map(json::document_from_json)
+                        .collect::<Result<_, _>>()?,
+                    next_page_token: take_string(&mut response, "nextPageToken")?
+                        .unwrap_or_default(),
+                })
+            }
+            Rpc::CreateDocument | Rpc::UpdateDocument => {
+                grpc_response(json::document_from_json(json)?)
+            }
+            Rpc::BeginTransaction => {
+                let mut response = json::expect_object(json, "response")?;
+                let transaction = take_string(&mut response, "transaction")?.ok_or(
+                    JsonError::UnexpectedType {
+                        field: "transaction",
+                        expected: "a string",
+                    },
+                )?;
+                grpc_response(firestore::BeginTransactionResponse {
+                    transaction: json::bytes_from_json(&transaction)?,
+                })
+            }
+            Rpc::Commit => {
+                let mut response = json::expect_object(json, "response")?;
+                grpc_response(firestore::CommitResponse {
+                    write_results: take_array(&mut response, "writeResults")?
+                        .into_iter()
+                        .map(write_result_from_json)
+                        .collect::<Result<_, _>>()?,
+                    commit_time: take_string(&mut response, "commitTime")?
+                        .map(|time| json::timestamp_from_json(&time))
+                        .transpose()?,
+                })
+            }
+            Rpc::Rollback => grpc_response(()),
+        })
+    }
+}
+
+/// Query parameters for a REST request.
+#[derive(Default)]
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn append(&mut self, name: &str, value: &str) {
+        self.0.push((name.to_string(), value.to_string()));
+    }
+
+    fn append_non_empty(&mut self, name: &str, value: &str) {
+        if !value.is_empty() {
+            self.append(name, value);
+        }
+    }
+
+    fn append_mask(&mut self, name: &str, mask: Option<&firestore::DocumentMask>) {
+        for field_path in mask.iter().flat_map(|mask| &mask.field_paths) {
+            self.append(&format!("{}.fieldPaths", name), field_path);
+        }
+    }
+}
+
+/// Reads the single message from a gRPC request body.
+async fn read_message(body: BoxBody) -> Result<Bytes, Status> {
+    let mut body = hyper::body::to_bytes(body).await?;
+    if body.len() < 5 || body[0] != 0 {
+        return Err(Status::internal(
+            "Expected a single uncompressed gRPC message",
+        ));
+    }
+
+    let length = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+    body.advance(5);
+    if body.len() != length {
+        return Err(Status::internal(
+            "Expected a single uncompressed gRPC message",
+        ));
+    }
+
+    Ok(body)
+}
+
+fn grpc_response(message: impl Message) -> Response<Body> {
+    let length = message.encoded_len();
+    let mut body = Vec::with_capacity(length + 5);
+    body.push(0);
+    body.extend_from_slice(&(length as u32).to_be_bytes());
+    message
+        .encode(&mut body)
+        .expect("a Vec to have enough capacity");
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/grpc")
+        // Sending the status up front means tonic won't wait for trailers.
+        .header("grpc-status", "0")
+        .body(Body::from(body))
+        .expect("a valid gRPC response")
+}
+
+/// The headers from a gRPC request that should be passed on, e.g.
+/// authorization, routing & any added by layers.
+fn forwarded_headers(
+    headers: &HeaderMap,
+) -> impl Iterator<Item = (&header::HeaderName, &header::HeaderValue)> {
+    headers.iter().filter(|(name, _)| {
+        *name != header::CONTENT_TYPE
+            && *name != header::CONTENT_LENGTH
+            && *name != header::TE
+            && !name.as_str().starts_with("grpc-")
+    })
+}
+
+/// Percent encodes a resource name for use in a URL path, leaving slashes as
+/// they are.
+fn encode_path(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'~'
+            | b'/'
+            | b'('
+            | b')'
+            | b':' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn write_to_json(write: &firestore::Write) -> Result<Json, Status> {
+    use firestore::{precondition::ConditionType, write::Operation};
+
+    let unsupported = || Status::unimplemented("The REST transport doesn't support transforms");
+
+    let mut object = Map::new();
+    match &write.operation {
+        Some(Operation::Update(document)) => {
+            object.insert("update".into(), json::document_to_json(document));
+        }
+        Some(Operation::Delete(name)) => {
+            object.insert("delete".into(), name.clone().into());
+        }
+        Some(Operation::Transform(_)) => return Err(unsupported()),
+        None => return Err(Status::invalid_argument("A write must have an operation")),
+    }
+    if !write.update_transforms.is_empty() {
+        return Err(unsupported());
+    }
+    if let Some(mask) = &write.update_mask {
+        object.insert(
+            "updateMask".into(),
+            json!({ "fieldPaths": mask.field_paths }),
+        );
+    }
+    match write
+        .current_document
+        .as_ref()
+        .and_then(|p| p.condition_type.as_ref())
+    {
+        Some(ConditionType::Exists(exists)) => {
+            object.insert("currentDocument".into(), json!({ "exists": exists }));
+        }
+        Some(ConditionType::UpdateTime(time)) => {
+            object.insert(
+                "currentDocument".into(),
+                json!({ "updateTime": json::timestamp_to_json(time) }),
+            );
+        }
+        None => {}
+    }
+
+    Ok(Json::Object(object))
+}
+
+fn transaction_options_to_json(options: &firestore::TransactionOptions) -> Json {
+    use firestore::transaction_options::{read_only::ConsistencySelector, Mode};
+
+    match &options.mode {
+        Some(Mode::ReadOnly(read_only)) => match &read_only.consistency_selector {
+            Some(ConsistencySelector::ReadTime(time)) => {
+                json!({ "readOnly": { "readTime": json::timestamp_to_json(time) } })
+            }
+            None => json!({ "readOnly": {} }),
+        },
+        Some(Mode::ReadWrite(read_write)) if !read_write.retry_transaction.is_empty() => {
+            json!({
+                "readWrite": {
+                    "retryTransaction": json::bytes_to_json(&read_write.retry_transaction)
+                }
+            })
+        }
+        Some(Mode::ReadWrite(_)) => json!({ "readWrite": {} }),
+        None => json!({}),
+    }
+}
+
+fn write_result_from_json(json: Json) -> Result<firestore::WriteResult, JsonError> {
+    let mut result = json::expect_object(json, "writeResult")?;
+
+    Ok(firestore::WriteResult {
+        update_time: take_string(&mut result, "updateTime")?
+            .map(|time| json::timestamp_from_json(&time))
+            .transpose()?,
+        transform_results: take_array(&mut result, "transformResults")?
+            .into_iter()
+            .map(json::value_from_json)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn take_array(object: &mut Map<String, Json>, field: &'static str) -> Result<Vec<Json>, JsonError> {
+    match object.remove(field) {
+        Some(Json::Array(values)) => Ok(values),
+        Some(_) => Err(JsonError::UnexpectedType {
+            field,
+            expected: "an array",
+        }),
+        None => Ok(vec![]),
+    }
+}
+
+fn take_string(
+    object: &mut Map<String, Json>,
+    field: &'static str,
+) -> Result<Option<String>, JsonError> {
+    object
+        .remove(field)
+        .map(|value| json::expect_string(value, field))
+        .transpose()
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    details: Vec<Json>,
+}
+
+/// Converts a REST error into a gRPC status.
+///
+/// The `google.rpc` details that `ErrorDetails` understands are kept, any
+/// others are dropped.
+fn error_status(status: StatusCode, body: &[u8]) -> Status {
+    let error = match serde_json::from_slice::<ErrorResponse>(body) {
+        Ok(response) => response.error,
+        Err(_) => {
+            return Status::new(
+                code_from_http_status(status),
+                format!("REST request failed with {}", status),
+            )
+        }
+    };
+
+    let code = code_from_name(&error.status).unwrap_or_else(|| code_from_http_status(status));
+    let details = error
+        .details
+        .into_iter()
+        .filter_map(detail_from_json)
+        .collect::<Vec<_>>();
+    if details.is_empty() {
+        return Status::new(code, error.message);
+    }
+
+    let encoded = rpc::Status {
+        code: code as i32,
+        message: error.message.clone(),
+        details,
+    }
+    .encode_to_vec();
+
+    Status::with_details(code, error.message, Bytes::from(encoded))
+}
+
+/// Re-encodes an error detail from the proto3 JSON mapping as protobuf.
+fn detail_from_json(detail: Json) -> Option<prost_types::Any> {
+    // The fields of every detail type we support, which don't overlap
+    #[derive(Deserialize, Default)]
+    #[serde(default, rename_all = "camelCase")]
+    struct Detail {
+        #[serde(rename = "@type")]
+        type_url: String,
+        reason: String,
+        domain: String,
+        metadata: HashMap<String, String>,
+        retry_delay: Option<String>,
+        violations: Vec<Violation>,
+        field_violations: Vec<FieldViolation>,
+        request_id: String,
+        serving_data: String,
+        resource_type: String,
+        resource_name: String,
+        owner: String,
+        description: String,
+        links: Vec<Link>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    struct Violation {
+        #[serde(rename = "type")]
+        kind: String,
+        subject: String,
+        description: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    struct FieldViolation {
+        field: String,
+        description: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    struct Link {
+        description: String,
+        url: String,
+    }
+
+    let detail = serde_json::from_value::<Detail>(detail).ok()?;
+    let value = match detail.type_url.strip_prefix(TYPE_URL_PREFIX)? {
+        "google.rpc.ErrorInfo" => rpc::ErrorInfo {
+            reason: detail.reason,
+            domain: detail.domain,
+            metadata: detail.metadata,
+        }
+        .encode_to_vec(),
+        "google.rpc.RetryInfo" => rpc::RetryInfo {
+            retry_delay: detail.retry_delay.as_deref().and_then(duration_from_json),
+        }
+        .encode_to_vec(),
+        "google.rpc.BadRequest" => rpc::BadRequest {
+            field_violations: detail
+                .field_violations
+                .into_iter()
+                .map(|v| rpc::bad_request::FieldViolation {
+                    field: v.field,
+                    description: v.description,
+                })
+                .collect(),
+        }
+        .encode_to_vec(),
+        "google.rpc.QuotaFailure" => rpc::QuotaFailure {
+            violations: detail
+                .violations
+                .into_iter()
+                .map(|v| rpc::quota_failure::Violation {
+                    subject: v.subject,
+                    description: v.description,
+                })
+                .collect(),
+        }
+        .encode_to_vec(),
+        "google.rpc.PreconditionFailure" => rpc::PreconditionFailure {
+            violations: detail
+                .violations
+                .into_iter()
+                .map(|v| rpc::precondition_failure::Violation {
+                    r#type: v.kind,
+                    subject: v.subject,
+                    description: v.description,
+                })
+                .collect(),
+        }
+        .encode_to_vec(),
+        "google.rpc.ResourceInfo" => rpc::ResourceInfo {
+            resource_type: detail.resource_type,
+            resource_name: detail.resource_name,
+            owner: detail.owner,
+            description: detail.description,
+        }
+        .encode_to_vec(),
+        "google.rpc.RequestInfo" => rpc::RequestInfo {
+            request_id: detail.request_id,
+            serving_data: detail.serving_data,
+        }
+        .encode_to_vec(),
+        "google.rpc.Help" => rpc::Help {
+            links: detail
+                .links
+                .into_iter()
+                .map(|l| rpc::help::Link {
+                    description: l.description,
+                    url: l.url,
+                })
+                .collect(),
+        }
+        .encode_to_vec(),
+        _ => return None,
+    };
+
+    Some(prost_types::Any {
+        type_url: detail.type_url,
+        value,
+    })
+}
+
+/// Parses a JSON duration, e.g. `1.5s`.
+fn duration_from_json(duration: &str) -> Option<prost_types::Duration> {
+    let seconds = duration.strip_suffix('s')?.parse::<f64>().ok()?;
+    // Also rules out NaN, and anything too big for a Duration
+    if !(0.0..i64::MAX as f64).contains(&seconds) {
+        return None;
+    }
+
+    let duration = Duration::from_secs_f64(seconds);
+    Some(prost_types::Duration {
+        seconds: duration.as_secs() as i64,
+        nanos: duration.subsec_nanos() as i32,
+    })
+}
+
+fn code_from_name(name: &str) -> Option<Code> {
+    Some(match name {
+        "OK" => Code::Ok,
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        _ => return None,
+    })
+}
+
+/// The gRPC code for an HTTP status, for errors that don't say.
+///
+/// See https://github.com/googleapis/googleapis/blob/master/google/rpc/code.proto
+fn code_from_http_status(status: StatusCode) -> Code {
+    match status.as_u16() {
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        409 => Code::Aborted,
+        412 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        500 => Code::Internal,
+        501 => Code::Unimplemented,
+        503 => Code::Unavailable,
+        504 => Code::DeadlineExceeded,
+        _ => Code::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Server,
+    };
+
+    use super::*;
+    use crate::{
+        operations::OperationError,
+        transactions::ReadPhaseExecutor,
+        values::{DocumentValues, Value},
+        CollectionRef, Database, DatabaseBuilder, ErrorDetails, FirestoreError,
+    };
+
+    type Received = Arc<Mutex<Vec<(String, Json)>>>;
+
+    /// Starts a local HTTP server that replies to each request with the next
+    /// of `responses`, and a Database that talks to it over REST.
+    ///
+    /// Returns the Database & the method, URI and body of each request the
+    /// server receives.
+    fn serve(responses: Vec<(u16, Json)>) -> (Database, Received) {
+        let responses = Arc::new(Mutex::new(VecDeque::from(responses)));
+        let received = Received::default();
+
+        let make_service = {
+            let received = Arc::clone(&received);
+            make_service_fn(move |_| {
+                let responses = Arc::clone(&responses);
+                let received = Arc::clone(&received);
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                        let responses = Arc::clone(&responses);
+                        let received = Arc::clone(&received);
+                        async move {
+                            assert_eq!(request.headers()[header::AUTHORIZATION], "Bearer owner");
+                            let summary = format!("{} {}", request.method(), request.uri());
+                            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                            let body = serde_json::from_slice(&body).unwrap_or(Json::Null);
+                            received.lock().unwrap().push((summary, body));
+
+                            let (status, json) = responses.lock().unwrap().pop_front().unwrap();
+                            let response = Response::builder()
+                                .status(status)
+                                .body(Body::from(json.to_string()))
+                                .unwrap();
+                            Ok::<_, Infallible>(response)
+                        }
+                    }))
+                }
+            })
+        };
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let database = DatabaseBuilder::new("test-project")
+            .transport(Transport::Rest)
+            .http_endpoint(&address.to_string())
+            .emulator_owner_credentials()
+            .connect_lazy()
+            .unwrap();
+
+        (database, received)
+    }
+
+    fn book(title: &str) -> DocumentValues {
+        DocumentValues::from_hashmap(maplit::hashmap! {
+            "title".to_string() => Value::String(title.to_string())
+        })
+    }
+
+    fn book_json(name: &str, title: &str) -> Json {
+        json!({
+            "name": format!("projects/test-project/databases/(default)/documents/books/{}", name),
+            "fields": { "title": { "stringValue": title } },
+            "createTime": "2021-08-01T12:00:00.123456Z",
+            "updateTime": "2021-08-01T12:00:00.123456Z",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_listing_documents() {
+        let (database, received) = serve(vec![
+            (
+                200,
+                json!({
+                    "documents": [book_json("northern-lights", "Northern Lights")],
+                    "nextPageToken": "page-2",
+                }),
+            ),
+            (200, json!({})),
+        ]);
+
+        let documents = CollectionRef::new("books")
+            .list_documents::<DocumentValues>()
+            .page_size(1)
+            .fetch_all(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].document, book("Northern Lights"));
+        insta::assert_debug_snapshot!(received.lock().unwrap(), @r###"
+        [
+            (
+                "GET /v1/projects/test-project/databases/(default)/documents/books?pageSize=1",
+                Null,
+            ),
+            (
+                "GET /v1/projects/test-project/databases/(default)/documents/books?pageSize=1&pageToken=page-2",
+                Null,
+            ),
+        ]
+        "###);
+    }
+
+    #[tokio::test]
+    async fn test_writing_documents() {
+        let (database, received) = serve(vec![
+            (200, book_json("northern-lights", "Northern Lights")),
+            (200, book_json("generated-id", "The Subtle Knife")),
+        ]);
+        let books = CollectionRef::new("books");
+
+        books
+            .document("northern-lights")
+            .set(&book("Northern Lights"))
+            .run(&database)
+            .await
+            .unwrap();
+        let added = books
+            .add_document(&book("The Subtle Knife"))
+            .run(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(added.document, book("The Subtle Knife"));
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                (
+                    "PATCH /v1/projects/test-project/databases/(default)/documents/books/northern-lights".to_string(),
+                    json!({
+                        "name": "projects/test-project/databases/(default)/documents/books/northern-lights",
+                        "fields": { "title": { "stringValue": "Northern Lights" } },
+                    })
+                ),
+                (
+                    "POST /v1/projects/test-project/databases/(default)/documents/books".to_string(),
+                    json!({ "fields": { "title": { "stringValue": "The Subtle Knife" } } })
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transactions() {
+        let (database, received) = serve(vec![
+            (200, json!({ "transaction": "dHgtMQ==" })),
+            (200, json!({})),
+            (
+                200,
+                json!({
+                    "writeResults": [{ "updateTime": "2021-08-01T12:00:00Z" }],
+                    "commitTime": "2021-08-01T12:00:00Z",
+                }),
+            ),
+        ]);
+
+        database
+            .transaction()
+            .run(|tx: ReadPhaseExecutor| async move {
+                let books = CollectionRef::new("books");
+                books
+                    .list_documents::<DocumentValues>()
+                    .fetch_all(&tx)
+                    .await
+                    .unwrap();
+
+                let tx = tx.finish_reads();
+                books
+                    .add_document(&book("Northern Lights"))
+                    .run_in(&tx)
+                    .await
+                    .unwrap();
+            })
+            .await
+            .unwrap();
+
+        let mut received = received.lock().unwrap();
+        let name = &mut received[2].1["writes"][0]["update"]["name"];
+        assert!(name
+            .as_str()
+            .unwrap()
+            .starts_with("projects/test-project/databases/(default)/documents/books/"));
+        *name = "<generated>".into();
+
+        assert_eq!(
+            *received,
+            vec![
+                (
+                    "POST /v1/projects/test-project/databases/(default)/documents:beginTransaction".to_string(),
+                    json!({ "options": { "readWrite": {} } })
+                ),
+                (
+                    "GET /v1/projects/test-project/databases/(default)/documents/books?transaction=dHgtMQ%3D%3D".to_string(),
+                    Json::Null
+                ),
+                (
+                    "POST /v1/projects/test-project/databases/(default)/documents:commit".to_string(),
+                    json!({
+                        "writes": [{
+                            "update": {
+                                "name": "<generated>",
+                                "fields": { "title": { "stringValue": "Northern Lights" } },
+                            },
+                        }],
+                        "transaction": "dHgtMQ==",
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (database, _) = serve(vec![(
+            409,
+            json!({
+                "error": {
+                    "code": 409,
+                    "message": "Document already exists",
+                    "status": "ALREADY_EXISTS",
+                    "details": [
+                        {
+                            "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                            "reason": "EXISTS",
+                            "domain": "firestore.googleapis.com",
+                        },
+                        {
+                            "@type": "type.googleapis.com/google.rpc.RetryInfo",
+                            "retryDelay": "1.500s",
+                        },
+                        {
+                            "@type": "type.googleapis.com/google.rpc.RequestInfo",
+                            "requestId": "abc123",
+                        },
+                        {
+                            "@type": "type.googleapis.com/google.rpc.PreconditionFailure",
+                            "violations": [
+                                { "type": "EXISTS", "subject": "books/northern-lights" },
+                            ],
+                        },
+                        {
+                            "@type": "type.googleapis.com/google.rpc.DebugInfo",
+                            "detail": "ignored",
+                        },
+                    ],
+                },
+            }),
+        )]);
+
+        let error = CollectionRef::new("books")
+            .add_document(&book("Northern Lights"))
+            .run(&database)
+            .await
+            .unwrap_err();
+
+        let details = match error {
+            OperationError::FirestoreError(FirestoreError::AlreadyExists(details)) => details,
+            other => panic!("Expected AlreadyExists, got {:?}", other),
+        };
+        assert_eq!(details.message, "Document already exists");
+        assert_eq!(details.error_info.unwrap().reason, "EXISTS");
+        assert_eq!(details.retry_delay, Some(Duration::from_millis(1500)));
+        assert_eq!(details.request_id.as_deref(), Some("abc123"));
+        assert_eq!(details.precondition_violations[0].kind, "EXISTS");
+    }
+
+    #[test]
+    fn test_error_details() {
+        let details = |details: Json| {
+            let body = json!({
+                "error": {
+                    "message": "The query requires an index",
+                    "status": "FAILED_PRECONDITION",
+                    "details": [details],
+                },
+            });
+            let status = error_status(StatusCode::BAD_REQUEST, body.to_string().as_bytes());
+            ErrorDetails::from_status(&status)
+        };
+
+        let help = details(json!({
+            "@type": "type.googleapis.com/google.rpc.Help",
+            "links": [{
+                "description": "Create the index",
+                "url": "https://console.firebase.google.com/project/test/firestore/indexes?create_composite=abc",
+            }],
+        }));
+        assert_eq!(
+            help.index_creation_url(),
+            Some("https://console.firebase.google.com/project/test/firestore/indexes?create_composite=abc")
+        );
+
+        let bad_request = details(json!({
+            "@type": "type.googleapis.com/google.rpc.BadRequest",
+            "fieldViolations": [{ "field": "page_size", "description": "too big" }],
+        }));
+        assert_eq!(bad_request.field_violations[0].field, "page_size");
+
+        let quota = details(json!({
+            "@type": "type.googleapis.com/google.rpc.QuotaFailure",
+            "violations": [{ "subject": "project:test", "description": "Daily limit" }],
+        }));
+        assert_eq!(quota.quota_violations[0].subject, "project:test");
+
+        let resource = details(json!({
+            "@type": "type.googleapis.com/google.rpc.ResourceInfo",
+            "resourceType": "document",
+            "resourceName": "books/northern-lights",
+        }));
+        assert_eq!(
+            resource.resource_info.unwrap().resource_name,
+            "books/northern-lights"
+        );
+    }
+
+    #[test]
+    fn test_durations_from_json() {
+        assert_eq!(
+            duration_from_json("1.5s"),
+            Some(prost_types::Duration {
+                seconds: 1,
+                nanos: 500_000_000
+            })
+        );
+        assert_eq!(duration_from_json("1e20s"), None);
+        assert_eq!(duration_from_json("-1s"), None);
+        assert_eq!(duration_from_json("NaNs"), None);
+        assert_eq!(duration_from_json("1.5"), None);
+    }
+
+    #[test]
+    fn test_error_without_json() {
+        let status = error_status(StatusCode::SERVICE_UNAVAILABLE, b"<html>Bad gateway</html>");
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(
+            status.message(),
+            "REST request failed with 503 Service Unavailable"
+        );
+    }
+}
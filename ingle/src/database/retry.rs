@@ -0,0 +1,301 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::FirestoreError;
+
+/// Controls how idempotent requests are retried when they fail with a
+/// transient error.
+///
+/// Only requests that are safe to repeat are retried - e.g. listing documents
+/// or setting a document.  Creating a document is never retried, as it might
+/// have succeeded despite returning an error.
+///
+/// Requests are retried when Firestore returns `Unavailable`,
+/// `DeadlineExceeded`, `ResourceExhausted` or `Internal`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    total_deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 1.3,
+            jitter: 0.5,
+            total_deadline: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Sets the maximum number of attempts, including the first.
+    ///
+    /// Default is 5.
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    /// Sets how long to wait before the first retry.
+    ///
+    /// Default is 100ms.
+    pub fn initial_backoff(self, initial_backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff,
+            ..self
+        }
+    }
+
+    /// Sets the longest we'll ever wait between two attempts.
+    ///
+    /// Default is 60 seconds.
+    pub fn max_backoff(self, max_backoff: Duration) -> Self {
+        RetryPolicy {
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// Sets the factor the backoff is multiplied by after each retry.
+    ///
+    /// Default is 1.3.
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        RetryPolicy { multiplier, ..self }
+    }
+
+    /// Sets how much randomness is applied to each backoff.
+    ///
+    /// This is a fraction between 0 and 1 - each backoff will be reduced by a
+    /// random amount up to that fraction of itself.  A jitter of 0 disables
+    /// randomisation entirely.
+    ///
+    /// Default is 0.5.
+    pub fn jitter(self, jitter: f64) -> Self {
+        RetryPolicy {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Sets the total time we'll spend retrying a request.
+    ///
+    /// No retry will be attempted if it'd start after the deadline.  If None
+    /// is specified we'll retry until `max_attempts` is reached.
+    ///
+    /// Default is 10 minutes.
+    pub fn total_deadline(self, total_deadline: impl Into<Option<Duration>>) -> Self {
+        RetryPolicy {
+            total_deadline: total_deadline.into(),
+            ..self
+        }
+    }
+
+    /// Runs a request, retrying if it fails with a retryable error.
+    pub(crate) async fn retry<F, Fut, T>(&self, request: F) -> Result<T, FirestoreError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, FirestoreError>>,
+    {
+        self.retry_if(request, is_retryable, FirestoreError::retry_delay)
+            .await
+    }
+
+    /// Runs a request, retrying while `should_retry` returns true for its errors.
+    ///
+    /// `server_delay` can return a minimum delay requested by the server, which
+    /// takes precedence over our own backoff if it's longer.
+    pub(crate) async fn retry_if<F, Fut, T, E>(
+        &self,
+        mut request: F,
+        should_retry: impl Fn(&E) -> bool,
+        server_delay: impl Fn(&E) -> Option<Duration>,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let started_at = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            super::trace::record_attempt(attempt);
+
+            let error = match request().await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+
+            if attempt >= self.max_attempts || !should_retry(&error) {
+                return Err(error);
+            }
+
+            let mut delay = self.backoff(attempt);
+            if let Some(server_delay) = server_delay(&error) {
+                delay = delay.max(server_delay);
+            }
+
+            if let Some(deadline) = self.total_deadline {
+                if started_at.elapsed() + delay > deadline {
+                    return Err(error);
+                }
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Calculates how long to wait after the given attempt failed.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+
+        let jitter = if self.jitter > 0.0 {
+            rand::thread_rng().gen_range(0.0..=self.jitter)
+        } else {
+            0.0
+        };
+
+        Duration::from_secs_f64(backoff * (1.0 - jitter))
+    }
+}
+
+fn is_retryable(error: &FirestoreError) -> bool {
+    matches!(
+        error,
+        FirestoreError::Unavailable(_)
+            | FirestoreError::DeadlineExceeded(_)
+            | FirestoreError::ResourceExhausted(_)
+            | FirestoreError::Internal(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn quick_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(1))
+            .max_backoff(Duration::from_millis(5))
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300))
+            .multiplier(2.0)
+            .jitter(0.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(10), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .jitter(0.5);
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(50));
+            assert!(backoff <= Duration::from_millis(100));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let attempts = AtomicU32::new(0);
+
+        let result = quick_policy()
+            .retry(|| async {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(FirestoreError::Unavailable(Default::default()))
+                } else {
+                    Ok("done")
+                }
+            })
+            .await;
+
+        assert_eq!(result, Ok("done"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = quick_policy()
+            .max_attempts(3)
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(FirestoreError::Internal(Default::default()))
+            })
+            .await;
+
+        assert_eq!(result, Err(FirestoreError::Internal(Default::default())));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_doesnt_retry_other_errors() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = quick_policy()
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(FirestoreError::NotFound(Default::default()))
+            })
+            .await;
+
+        assert_eq!(result, Err(FirestoreError::NotFound(Default::default())));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_respects_total_deadline() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = RetryPolicy::default()
+            .initial_backoff(Duration::from_secs(60))
+            .total_deadline(Duration::from_secs(1))
+            .jitter(0.0)
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(FirestoreError::Unavailable(Default::default()))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,115 @@
+use rustls::{internal::pemfile, ClientConfig};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+/// TLS settings for connecting to Firestore.
+///
+/// By default the system root certificates are trusted.  Any CA certificates
+/// added here are trusted in addition to those, which is useful when going
+/// through a TLS intercepting proxy.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    ca_certificates: Vec<u8>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    domain_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        TlsConfig::default()
+    }
+
+    /// Trusts the CA certificates in a PEM encoded bundle.
+    ///
+    /// Can be called more than once to add several bundles.
+    pub fn ca_certificates_pem(mut self, pem: impl AsRef<[u8]>) -> Self {
+        if !self.ca_certificates.is_empty() && !self.ca_certificates.ends_with(b"\n") {
+            self.ca_certificates.push(b'\n');
+        }
+        self.ca_certificates.extend_from_slice(pem.as_ref());
+        self
+    }
+
+    /// Sets a PEM encoded client certificate & private key to present to the
+    /// server, for mutual TLS.
+    pub fn client_identity_pem(self, certificate: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Self {
+        TlsConfig {
+            identity: Some((certificate.as_ref().to_vec(), key.as_ref().to_vec())),
+            ..self
+        }
+    }
+
+    /// Overrides the domain name the servers certificate is checked against.
+    ///
+    /// Defaults to the host of the endpoint.
+    pub fn domain_name(self, domain_name: impl Into<String>) -> Self {
+        TlsConfig {
+            domain_name: Some(domain_name.into()),
+            ..self
+        }
+    }
+
+    pub(crate) fn into_client_config(self) -> ClientTlsConfig {
+        let mut config = ClientTlsConfig::new();
+
+        if !self.ca_certificates.is_empty() {
+            config = config.ca_certificate(Certificate::from_pem(self.ca_certificates));
+        }
+        if let Some((certificate, key)) = self.identity {
+            config = config.identity(Identity::from_pem(certificate, key));
+        }
+        if let Some(domain_name) = self.domain_name {
+            config = config.domain_name(domain_name);
+        }
+
+        config
+    }
+
+    /// Builds a rustls config for the REST transport, which only speaks
+    /// HTTP/1.1.
+    pub(crate) fn into_rustls_config(self) -> Result<ClientConfig, String> {
+        if self.domain_name.is_some() {
+            return Err("the REST transport can't override the domain name".into());
+        }
+
+        let mut config = ClientConfig::new();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        config.root_store = match rustls_native_certs::load_native_certs() {
+            Ok(store) | Err((Some(store), _)) => store,
+            Err((None, error)) => {
+                return Err(format!("couldn't load root certificates: {}", error))
+            }
+        };
+
+        if !self.ca_certificates.is_empty() {
+            config
+                .root_store
+                .add_pem_file(&mut self.ca_certificates.as_slice())
+                .map_err(|_| "invalid CA certificates".to_string())?;
+        }
+        if let Some((certificate, key)) = self.identity {
+            let certificates = pemfile::certs(&mut certificate.as_slice())
+                .map_err(|_| "invalid client certificate".to_string())?;
+            let key = pemfile::pkcs8_private_keys(&mut key.as_slice())
+                .ok()
+                .filter(|keys| !keys.is_empty())
+                .or_else(|| pemfile::rsa_private_keys(&mut key.as_slice()).ok())
+                .and_then(|keys| keys.into_iter().next())
+                .ok_or_else(|| "invalid client private key".to_string())?;
+            config
+                .set_single_client_cert(certificates, key)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(config)
+    }
+
+    /// Builds a rustls config for fetching access tokens.  The domain name
+    /// override is only meant for Firestore, so it's left out.
+    pub(crate) fn into_token_config(self) -> Result<ClientConfig, String> {
+        TlsConfig {
+            domain_name: None,
+            ..self
+        }
+        .into_rustls_config()
+    }
+}
@@ -0,0 +1,222 @@
+//! Tracing spans for RPCs & transactions.
+//!
+//! These are no-ops unless the `tracing` feature is enabled.  Spans only
+//! record paths, sizes, attempts & result codes - never document contents.
+
+use std::future::Future;
+
+use super::{transactions::TransactionError, FirestoreError};
+use crate::paths::{CollectionPath, DocumentPath, ProjectPath};
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument};
+
+#[derive(Clone)]
+pub struct Span {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl Span {
+    /// A span for a single Firestore RPC, including any retries.
+    pub fn rpc(rpc: &'static str, project_path: &ProjectPath) -> Span {
+        Span {
+            span: tracing::debug_span!(
+                "firestore_rpc",
+                rpc,
+                database = project_path.database_path(),
+                collection = Empty,
+                document = Empty,
+                page_size = Empty,
+                attempt = Empty,
+                code = Empty,
+                documents = Empty,
+            ),
+        }
+    }
+
+    /// A span covering every attempt at a transaction.
+    pub fn transaction(mode: &'static str, project_path: &ProjectPath) -> Span {
+        Span {
+            span: tracing::debug_span!(
+                "firestore_transaction",
+                mode,
+                database = project_path.database_path(),
+                attempts = Empty,
+                code = Empty,
+            ),
+        }
+    }
+
+    pub fn transaction_attempt(attempt: u32) -> Span {
+        Span {
+            span: tracing::debug_span!(
+                "firestore_transaction_attempt",
+                attempt,
+                code = Empty,
+                writes = Empty,
+            ),
+        }
+    }
+
+    pub fn record_collection(&self, path: &CollectionPath) {
+        self.span
+            .record("collection", path.relative_path().as_str());
+    }
+
+    pub fn record_document(&self, path: &DocumentPath) {
+        self.span.record("document", path.relative_path());
+    }
+
+    pub fn record_page_size(&self, page_size: i32) {
+        self.span.record("page_size", page_size);
+    }
+
+    pub fn record_documents(&self, documents: usize) {
+        self.span.record("documents", documents as u64);
+    }
+
+    pub fn record_writes(&self, writes: usize) {
+        self.span.record("writes", writes as u64);
+    }
+
+    pub fn record_attempts(&self, attempts: u32) {
+        self.span.record("attempts", attempts);
+    }
+
+    pub fn record_result<T>(&self, result: &Result<T, FirestoreError>) {
+        let code = match result {
+            Ok(_) => "OK",
+            Err(error) => error.code_name(),
+        };
+        self.span.record("code", code);
+    }
+
+    pub fn record_transaction_result<T>(&self, result: &Result<T, TransactionError>) {
+        let code = match result {
+            Ok(_) => "OK",
+            Err(error) => error.code_name(),
+        };
+        self.span.record("code", code);
+    }
+
+    pub async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        future.instrument(self.span.clone()).await
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_variables)]
+impl Span {
+    pub fn rpc(rpc: &'static str, project_path: &ProjectPath) -> Span {
+        Span {}
+    }
+
+    pub fn transaction(mode: &'static str, project_path: &ProjectPath) -> Span {
+        Span {}
+    }
+
+    pub fn transaction_attempt(attempt: u32) -> Span {
+        Span {}
+    }
+
+    pub fn record_collection(&self, path: &CollectionPath) {}
+
+    pub fn record_document(&self, path: &DocumentPath) {}
+
+    pub fn record_page_size(&self, page_size: i32) {}
+
+    pub fn record_documents(&self, documents: usize) {}
+
+    pub fn record_writes(&self, writes: usize) {}
+
+    pub fn record_attempts(&self, attempts: u32) {}
+
+    pub fn record_result<T>(&self, result: &Result<T, FirestoreError>) {}
+
+    pub fn record_transaction_result<T>(&self, result: &Result<T, TransactionError>) {}
+
+    pub async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        future.await
+    }
+}
+
+/// Records which attempt at an RPC is running on the current span.
+pub fn record_attempt(attempt: u32) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("attempt", attempt);
+
+    #[cfg(not(feature = "tracing"))]
+    let _ = attempt;
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Subscriber,
+    };
+
+    use super::*;
+
+    /// A subscriber that records the fields of every span as strings.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        fields: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.fields
+                .lock()
+                .unwrap()
+                .push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            span.record(&mut self.clone());
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &tracing::Event<'_>) {}
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_rpc_span() {
+        let recorder = Recorder::default();
+        let project_path = ProjectPath::new("test".into(), "(default)".into());
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let span = Span::rpc("ListDocuments", &project_path);
+            span.record_collection(&CollectionPath::new("books".into()));
+            span.record_page_size(10);
+            span.record_result::<()>(&Err(FirestoreError::NotFound(Default::default())));
+        });
+
+        insta::assert_debug_snapshot!(recorder.fields.lock().unwrap(), @r###"
+        [
+            "rpc=\"ListDocuments\"",
+            "database=\"projects/test/databases/(default)\"",
+            "collection=\"/books\"",
+            "page_size=10",
+            "code=\"NOT_FOUND\"",
+        ]
+        "###);
+    }
+}
@@ -64,10 +64,26 @@ impl WritePhaseExecutor {
 
 #[async_trait]
 impl BatchWriteExecutor for WritePhaseExecutor {
-    async fn add_document(&self, input: operations::AddDocumentRequest) {
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<(), FirestoreError> {
         self.writes
             .unbounded_send(WriteRequest::AddDocument(input))
             .expect("unbounded_send failed in add_document");
+
+        Ok(())
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        self.writes
+            .unbounded_send(WriteRequest::SetDocument(input))
+            .expect("unbounded_send failed in set_document");
+
+        Ok(())
     }
 }
 
@@ -75,6 +91,7 @@ pub(super) enum WriteRequest {
     Commit,
     Rollback,
     AddDocument(operations::AddDocumentRequest),
+    SetDocument(operations::SetDocumentRequest),
 }
 
 impl WriteRequest {
@@ -87,6 +104,9 @@ impl WriteRequest {
             WriteRequest::AddDocument(request) => {
                 Some(request.into_firestore_write(project_path.clone()))
             }
+            WriteRequest::SetDocument(request) => {
+                Some(request.into_firestore_write(project_path.clone()))
+            }
         }
     }
 }
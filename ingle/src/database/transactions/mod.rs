@@ -1,15 +1,25 @@
 use std::future::Future;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use futures_channel::mpsc;
 use futures_util::StreamExt;
-
-use crate::{google::firestore::v1 as firestore, paths::ProjectPath, Database, FirestoreError};
+use tokio::time::Instant;
+
+use super::{
+    metrics::{CommitCounts, Metrics},
+    trace,
+};
+use crate::{
+    google::firestore::v1 as firestore,
+    paths::ProjectPath,
+    values::{DecodingError, Timestamp, Value},
+    Database, FirestoreError,
+};
 
 mod executors;
 
-pub use executors::{ReadOnlyExecutor, ReadPhaseExecutor};
+pub use executors::{ReadOnlyExecutor, ReadPhaseExecutor, WritePhaseExecutor};
 
 use self::executors::WriteRequest;
 
@@ -18,6 +28,7 @@ impl super::Database {
         TransactionBuilder {
             database: self.clone(),
             mode: ReadWrite::default(),
+            timeout: None,
         }
     }
 }
@@ -67,6 +78,7 @@ where
 pub struct TransactionBuilder<Mode = ReadWrite> {
     database: Database,
     mode: Mode,
+    timeout: Option<Duration>,
 }
 
 impl<Mode> TransactionBuilder<Mode> {
@@ -74,6 +86,7 @@ impl<Mode> TransactionBuilder<Mode> {
         TransactionBuilder {
             mode: ReadOnly::default(),
             database: self.database,
+            timeout: self.timeout,
         }
     }
 
@@ -81,8 +94,35 @@ impl<Mode> TransactionBuilder<Mode> {
         TransactionBuilder {
             mode: ReadWrite::default(),
             database: self.database,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets a deadline for the transaction.
+    ///
+    /// If the transaction hasn't committed when the timeout elapses it'll be
+    /// cancelled, a rollback will be sent in the background and `run` will
+    /// return `TransactionError::TimedOut`.  The timeout covers beginning the
+    /// transaction, the transaction closure & the commit of every attempt,
+    /// not each attempt individually.
+    ///
+    /// If the timeout elapses while the commit is in flight Firestore may
+    /// still have applied it, so after a `TimedOut` error the transaction's
+    /// writes may or may not have been made.
+    ///
+    /// Firestore will expire transactions that are idle for too long and
+    /// has a hard limit of 270 seconds, so this should usually be set
+    /// lower than that.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        TransactionBuilder {
+            timeout: Some(timeout),
+            ..self
         }
     }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
 }
 
 impl TransactionBuilder<ReadWrite> {
@@ -95,85 +135,179 @@ impl TransactionBuilder<ReadWrite> {
         }
     }
 
-    pub async fn run<T>(self, transaction: T) -> Result<T::Result, TransactionError>
+    pub async fn run<T>(
+        self,
+        transaction: T,
+    ) -> Result<TransactionOutcome<T::Result>, TransactionError>
+    where
+        T: Transaction,
+    {
+        let span = trace::Span::transaction("read_write", &self.database.project_path);
+
+        let result = span.instrument(self.run_attempts(&span, transaction)).await;
+
+        span.record_transaction_result(&result);
+        result
+    }
+
+    async fn run_attempts<T>(
+        self,
+        span: &trace::Span,
+        transaction: T,
+    ) -> Result<TransactionOutcome<T::Result>, TransactionError>
     where
         T: Transaction,
     {
         let mut client = self.database.client.clone();
-        let mut tries = self.mode.tries();
         let mut transaction_id = vec![];
         let mut last_error = None;
-        let database_path = self.database.project_path.database_path().to_string();
-
-        while tries > 0 {
-            tries -= 1;
+        let deadline = self.deadline();
+
+        for attempt in 1..=u32::from(self.mode.tries()) {
+            span.record_attempts(attempt);
+
+            let attempt_span = trace::Span::transaction_attempt(attempt);
+            let result = attempt_span
+                .instrument(self.attempt(
+                    &attempt_span,
+                    &mut client,
+                    &transaction,
+                    &mut transaction_id,
+                    deadline,
+                ))
+                .await;
+            attempt_span.record_transaction_result(&result);
+
+            match result {
+                Err(e @ TransactionError::RollbackRequested)
+                | Err(e @ TransactionError::CouldNotCommitTransaction(_)) => {
+                    if attempt < u32::from(self.mode.tries()) {
+                        self.database.metrics.transaction_retried(e.code_name());
+                    }
+                    last_error = Some(e);
+                }
+                result => return result,
+            }
+        }
 
-            let begin_request = firestore::BeginTransactionRequest {
-                database: database_path.clone(),
-                options: Some(firestore::TransactionOptions {
-                    mode: Some(firestore::transaction_options::Mode::ReadWrite(
-                        firestore::transaction_options::ReadWrite {
-                            retry_transaction: transaction_id.clone(),
-                        },
-                    )),
-                }),
-            };
+        Err(last_error.expect("Transaction failed without error.  Probably a bug in ingle"))
+    }
 
-            let response = begin_transaction(&mut client, begin_request).await?;
+    /// Makes a single attempt at a transaction.
+    ///
+    /// `transaction_id` should be the ID of the previous attempt, if any, and
+    /// is updated with the ID of this attempt.
+    async fn attempt<T>(
+        &self,
+        span: &trace::Span,
+        client: &mut super::FirestoreClient,
+        transaction: &T,
+        transaction_id: &mut Vec<u8>,
+        deadline: Option<Instant>,
+    ) -> Result<TransactionOutcome<T::Result>, TransactionError>
+    where
+        T: Transaction,
+    {
+        let database_path = self.database.project_path.database_path().to_string();
 
-            transaction_id = response.transaction.clone();
+        let begin_request = firestore::BeginTransactionRequest {
+            database: database_path.clone(),
+            options: Some(firestore::TransactionOptions {
+                mode: Some(firestore::transaction_options::Mode::ReadWrite(
+                    firestore::transaction_options::ReadWrite {
+                        retry_transaction: transaction_id.clone(),
+                    },
+                )),
+            }),
+        };
 
-            let (write_sender, write_receiver) = mpsc::unbounded();
+        let response = with_deadline(
+            deadline,
+            begin_transaction(client, &self.database.metrics, begin_request),
+        )
+        .await??;
 
-            let executor = ReadPhaseExecutor {
-                database: self.database.clone(),
-                transaction_id: response.transaction,
-                writes: write_sender,
-            };
+        *transaction_id = response.transaction.clone();
 
-            let result = transaction.run(executor).await;
+        let guard = RollbackGuard::new(client, &database_path, transaction_id);
 
-            let writes = write_receiver.collect::<Vec<_>>().await;
+        let (write_sender, write_receiver) = mpsc::unbounded();
 
-            if writes
-                .iter()
-                .any(|w| matches!(w, executors::WriteRequest::Rollback))
-            {
-                client
-                    .rollback(firestore::RollbackRequest {
-                        database: database_path.clone(),
-                        transaction: transaction_id.clone(),
-                    })
-                    .await
-                    .ok();
+        let executor = ReadPhaseExecutor {
+            database: self.database.clone(),
+            transaction_id: response.transaction,
+            writes: write_sender,
+        };
 
-                last_error = Some(TransactionError::RollbackRequested);
-                continue;
+        let result = match with_deadline(deadline, transaction.run(executor)).await {
+            Ok(result) => result,
+            Err(e) => {
+                guard.rollback_after(&e).await;
+                return Err(e);
             }
+        };
 
-            let commit = commit_request(&self.database.project_path, &transaction_id, writes);
+        let writes = write_receiver.collect::<Vec<_>>().await;
+        span.record_writes(writes.len());
 
-            match commit_transaction(&mut client, commit).await {
-                Ok(_) => return Ok(result),
-                Err(e) => {
-                    last_error = Some(e);
-                    continue;
-                }
-            }
+        if writes
+            .iter()
+            .any(|w| matches!(w, executors::WriteRequest::Rollback))
+        {
+            guard.rollback().await;
+            return Err(TransactionError::RollbackRequested);
         }
 
-        Err(last_error.expect("Transaction failed without error.  Probably a bug in ingle"))
+        let commit = commit_request(&self.database.project_path, transaction_id, writes);
+        let counts = CommitCounts::new(&commit.writes);
+
+        match with_deadline(
+            deadline,
+            commit_transaction(client, &self.database.metrics, commit),
+        )
+        .await
+        {
+            Ok(Ok(response)) => {
+                guard.disarm();
+                self.database.metrics.documents_committed(counts);
+                Ok(TransactionOutcome::from_firestore(result, response))
+            }
+            Ok(Err(e)) | Err(e) => {
+                guard.rollback_after(&e).await;
+                Err(e)
+            }
+        }
     }
 }
 
 impl TransactionBuilder<ReadOnly> {
-    pub async fn run<T>(self, transaction: T) -> Result<T::Result, TransactionError>
+    pub async fn run<T>(
+        self,
+        transaction: T,
+    ) -> Result<TransactionOutcome<T::Result>, TransactionError>
+    where
+        T: ReadOnlyTransaction,
+    {
+        let span = trace::Span::transaction("read_only", &self.database.project_path);
+
+        let result = span.instrument(self.run_once(transaction)).await;
+
+        span.record_transaction_result(&result);
+        result
+    }
+
+    async fn run_once<T>(
+        self,
+        transaction: T,
+    ) -> Result<TransactionOutcome<T::Result>, TransactionError>
     where
         T: ReadOnlyTransaction,
     {
         let mut client = self.database.client.clone();
+        let database_path = self.database.project_path.database_path().to_string();
+        let deadline = self.deadline();
         let request = firestore::BeginTransactionRequest {
-            database: self.database.project_path.database_path().to_string(),
+            database: database_path.clone(),
             options: Some(firestore::TransactionOptions {
                 mode: Some(firestore::transaction_options::Mode::ReadOnly(
                     firestore::transaction_options::ReadOnly {
@@ -183,28 +317,112 @@ impl TransactionBuilder<ReadOnly> {
             }),
         };
 
-        let response = begin_transaction(&mut client, request).await?;
+        let response = with_deadline(
+            deadline,
+            begin_transaction(&mut client, &self.database.metrics, request),
+        )
+        .await??;
 
         let transaction_id = response.transaction.clone();
 
+        let guard = RollbackGuard::new(&client, &database_path, &transaction_id);
+
         let executor = ReadOnlyExecutor {
             database: self.database.clone(),
             transaction_id: response.transaction,
         };
 
-        let result = transaction.run(executor).await;
+        let result = match with_deadline(deadline, transaction.run(executor)).await {
+            Ok(result) => result,
+            Err(e) => {
+                guard.rollback_after(&e).await;
+                return Err(e);
+            }
+        };
 
-        commit_transaction(
-            &mut client,
-            firestore::CommitRequest {
-                database: self.database.project_path.database_path().to_string(),
-                transaction: transaction_id,
-                writes: Vec::new(),
-            },
+        let response = with_deadline(
+            deadline,
+            commit_transaction(
+                &mut client,
+                &self.database.metrics,
+                firestore::CommitRequest {
+                    database: database_path,
+                    transaction: transaction_id,
+                    writes: Vec::new(),
+                },
+            ),
         )
-        .await?;
+        .await;
+
+        match response {
+            Ok(response) => {
+                // Whether the commit succeeded or not there's nothing left to
+                // roll back
+                guard.disarm();
+                Ok(TransactionOutcome::from_firestore(result, response?))
+            }
+            Err(e) => {
+                guard.rollback_after(&e).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// The outcome of a successfully committed transaction.
+#[derive(Debug)]
+pub struct TransactionOutcome<T> {
+    /// The value returned from the transaction closure.
+    pub result: T,
+
+    /// The time at which the commit occurred.
+    ///
+    /// Any read with an equal or greater read time is guaranteed to see the
+    /// effects of the transaction.
+    pub commit_time: Option<Timestamp>,
+
+    /// The results of each write in the transaction, in the order the writes
+    /// were made.
+    pub write_results: Vec<WriteResult>,
+}
 
-        Ok(result)
+impl<T> TransactionOutcome<T> {
+    fn from_firestore(result: T, response: firestore::CommitResponse) -> Self {
+        TransactionOutcome {
+            result,
+            commit_time: response.commit_time.map(Timestamp::from_firestore),
+            write_results: response
+                .write_results
+                .into_iter()
+                .map(WriteResult::from_firestore)
+                .collect(),
+        }
+    }
+}
+
+/// The result of applying a single write in a transaction.
+#[derive(Debug, PartialEq)]
+pub struct WriteResult {
+    /// The last update time of the document after applying the write.
+    ///
+    /// If the write did not actually change the document this will be the
+    /// previous update time.
+    pub update_time: Option<Timestamp>,
+
+    /// The results of any field transforms applied by the write, in order.
+    pub transform_results: Vec<Result<Value, DecodingError>>,
+}
+
+impl WriteResult {
+    fn from_firestore(result: firestore::WriteResult) -> Self {
+        WriteResult {
+            update_time: result.update_time.map(Timestamp::from_firestore),
+            transform_results: result
+                .transform_results
+                .into_iter()
+                .map(Value::try_from_firestore)
+                .collect(),
+        }
     }
 }
 
@@ -216,6 +434,19 @@ pub enum TransactionError {
     CouldNotCommitTransaction(FirestoreError),
     #[error("A rollback was requested in the transaction")]
     RollbackRequested,
+    #[error("The transaction did not complete before its timeout")]
+    TimedOut,
+}
+
+impl TransactionError {
+    pub(crate) fn code_name(&self) -> &'static str {
+        match self {
+            TransactionError::CouldNotStartTransaction(error)
+            | TransactionError::CouldNotCommitTransaction(error) => error.code_name(),
+            TransactionError::RollbackRequested => "ROLLBACK_REQUESTED",
+            TransactionError::TimedOut => "TIMED_OUT",
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -236,28 +467,114 @@ impl ReadWrite {
 
 async fn begin_transaction(
     client: &mut super::FirestoreClient,
+    metrics: &Metrics,
     request: firestore::BeginTransactionRequest,
 ) -> Result<firestore::BeginTransactionResponse, TransactionError> {
-    Ok(client
-        .begin_transaction(request)
+    Ok(metrics
+        .observe("BeginTransaction", async {
+            client
+                .begin_transaction(request)
+                .await
+                .map_err(FirestoreError::from)
+        })
         .await
-        .map_err(FirestoreError::from)
         .map_err(TransactionError::CouldNotStartTransaction)?
         .into_inner())
 }
 
 async fn commit_transaction(
     client: &mut super::FirestoreClient,
+    metrics: &Metrics,
     request: firestore::CommitRequest,
 ) -> Result<firestore::CommitResponse, TransactionError> {
-    Ok(client
-        .commit(request)
+    Ok(metrics
+        .observe("Commit", async {
+            client.commit(request).await.map_err(FirestoreError::from)
+        })
         .await
-        .map_err(FirestoreError::from)
         .map_err(TransactionError::CouldNotCommitTransaction)?
         .into_inner())
 }
 
+async fn with_deadline<F>(
+    deadline: Option<Instant>,
+    future: F,
+) -> Result<F::Output, TransactionError>
+where
+    F: Future,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| TransactionError::TimedOut),
+        None => Ok(future.await),
+    }
+}
+
+/// How long to wait for a rollback before giving up on it.
+const ROLLBACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Rolls back a transaction unless it's disarmed.
+///
+/// If this is dropped without being disarmed (because the transaction closure
+/// panicked or the future running the transaction was cancelled) it'll spawn a
+/// best-effort rollback onto the current tokio runtime, so that we don't hold
+/// on to any locks until Firestore expires the transaction.
+struct RollbackGuard {
+    client: super::FirestoreClient,
+    request: Option<firestore::RollbackRequest>,
+}
+
+impl RollbackGuard {
+    fn new(client: &super::FirestoreClient, database: &str, transaction_id: &[u8]) -> Self {
+        RollbackGuard {
+            client: client.clone(),
+            request: Some(firestore::RollbackRequest {
+                database: database.to_string(),
+                transaction: transaction_id.to_vec(),
+            }),
+        }
+    }
+
+    fn disarm(mut self) {
+        self.request = None;
+    }
+
+    /// Rolls back the transaction, waiting up to `ROLLBACK_TIMEOUT` for it to
+    /// finish.
+    async fn rollback(mut self) {
+        if let Some(request) = self.request.take() {
+            tokio::time::timeout(ROLLBACK_TIMEOUT, self.client.rollback(request))
+                .await
+                .ok();
+        }
+    }
+
+    /// Rolls back the transaction after `error`.
+    ///
+    /// Transactions that timed out are rolled back in the background, so
+    /// `run` doesn't wait any longer than it has to.
+    async fn rollback_after(self, error: &TransactionError) {
+        match error {
+            TransactionError::TimedOut => drop(self),
+            _ => self.rollback().await,
+        }
+    }
+}
+
+impl Drop for RollbackGuard {
+    fn drop(&mut self) {
+        if let Some(request) = self.request.take() {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                let mut client = self.client.clone();
+                handle.spawn(async move {
+                    client.rollback(request).await.ok();
+                });
+            }
+        }
+    }
+}
+
 fn commit_request(
     project_path: &ProjectPath,
     transaction_id: &[u8],
@@ -272,3 +589,56 @@ fn commit_request(
             .collect(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_from_commit_response() {
+        let response = firestore::CommitResponse {
+            write_results: vec![firestore::WriteResult {
+                update_time: Some(prost_types::Timestamp {
+                    seconds: 10,
+                    nanos: 5,
+                }),
+                transform_results: vec![firestore::Value {
+                    value_type: Some(firestore::value::ValueType::IntegerValue(1)),
+                }],
+            }],
+            commit_time: Some(prost_types::Timestamp {
+                seconds: 11,
+                nanos: 0,
+            }),
+        };
+
+        insta::assert_debug_snapshot!(TransactionOutcome::from_firestore((), response), @r###"
+        TransactionOutcome {
+            result: (),
+            commit_time: Some(
+                Timestamp {
+                    seconds: 11,
+                    nandos: 0,
+                },
+            ),
+            write_results: [
+                WriteResult {
+                    update_time: Some(
+                        Timestamp {
+                            seconds: 10,
+                            nandos: 5,
+                        },
+                    ),
+                    transform_results: [
+                        Ok(
+                            Integer(
+                                1,
+                            ),
+                        ),
+                    ],
+                },
+            ],
+        }
+        "###);
+    }
+}
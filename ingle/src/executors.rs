@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 
-use crate::{document::DocumentResponse, operations, values::DocumentValues, FirestoreError};
+use crate::{
+    document::DocumentResponse, operations, values::DocumentValues, ErrorDetails, FirestoreError,
+};
 
 #[async_trait]
 pub trait ReadExecutor: Send + Sync {
@@ -67,8 +69,29 @@ where
 }
 
 #[async_trait]
+/// Writes documents as part of a batch.
+///
+/// Executors that only send their writes when the batch is committed can't
+/// report most errors here, and should return `Ok`.
 pub trait BatchWriteExecutor: Send + Sync {
-    async fn add_document(&self, input: operations::AddDocumentRequest);
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<(), FirestoreError>;
+
+    /// Fails with `FirestoreError::Unimplemented` unless overridden.
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        Err(FirestoreError::Unimplemented(Box::new(ErrorDetails {
+            message: format!(
+                "This executor can't set {} in a batch",
+                input.document_path().relative_path()
+            ),
+            ..ErrorDetails::default()
+        })))
+    }
 }
 
 #[async_trait]
@@ -76,9 +99,19 @@ impl<T> BatchWriteExecutor for &T
 where
     T: BatchWriteExecutor,
 {
-    async fn add_document(&self, input: operations::AddDocumentRequest) {
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<(), FirestoreError> {
         (*self).add_document(input).await
     }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        (*self).set_document(input).await
+    }
 }
 
 #[cfg(test)]
@@ -162,7 +195,7 @@ pub(crate) mod tests {
         ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
             self.list_documents_result
                 .take()
-                .unwrap_or(Err(FirestoreError::UnknownError))
+                .unwrap_or(Err(FirestoreError::UnknownError(Default::default())))
         }
     }
 
@@ -177,14 +210,14 @@ pub(crate) mod tests {
             &self,
             _: operations::AddDocumentRequest,
         ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
-            Err(FirestoreError::UnknownError)
+            Err(FirestoreError::UnknownError(Default::default()))
         }
 
         async fn set_document(
             &self,
             _: operations::SetDocumentRequest,
         ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
-            Err(FirestoreError::UnknownError)
+            Err(FirestoreError::UnknownError(Default::default()))
         }
     }
 
@@ -192,4 +225,29 @@ pub(crate) mod tests {
     fn write_executor_is_object_safe() {
         let _: Box<dyn WriteExecutor> = Box::new(TestExecutor::default());
     }
+
+    #[async_trait]
+    impl BatchWriteExecutor for TestExecutor {
+        async fn add_document(
+            &self,
+            _: operations::AddDocumentRequest,
+        ) -> Result<(), FirestoreError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_set_document_is_unimplemented_by_default() {
+        let error = crate::CollectionRef::new("books")
+            .document("northern-lights")
+            .set(&DocumentValues::from_hashmap(Default::default()))
+            .run_in(TestExecutor::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::Unimplemented(_))
+        ));
+    }
 }
@@ -0,0 +1,577 @@
+//! The proto3 JSON mapping of Firestore documents & values, as used by the
+//! REST API.
+//!
+//! See https://developers.google.com/protocol-buffers/docs/proto3#json for
+//! the rules - notably integers are strings, bytes are base64 and timestamps
+//! are RFC3339.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    google::{firestore::v1 as firestore, r#type::LatLng},
+    values::DecodingError,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum JsonError {
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("Expected {field} to be {expected}")]
+    UnexpectedType {
+        field: &'static str,
+        expected: &'static str,
+    },
+    #[error("Unknown value type {0}")]
+    UnknownValueType(String),
+    #[error("Invalid integer {0}")]
+    InvalidInteger(String),
+    #[error("Invalid timestamp {0}")]
+    InvalidTimestamp(String),
+    #[error("Invalid base64 in {0}")]
+    InvalidBytes(String),
+    #[error("{0}")]
+    DecodingError(#[from] DecodingError),
+}
+
+pub(crate) fn document_to_json(document: &firestore::Document) -> Json {
+    let mut object = Map::new();
+    if !document.name.is_empty() {
+        object.insert("name".into(), document.name.clone().into());
+    }
+    object.insert("fields".into(), fields_to_json(&document.fields));
+    if let Some(create_time) = &document.create_time {
+        object.insert("createTime".into(), timestamp_to_json(create_time).into());
+    }
+    if let Some(update_time) = &document.update_time {
+        object.insert("updateTime".into(), timestamp_to_json(update_time).into());
+    }
+
+    Json::Object(object)
+}
+
+pub(crate) fn document_from_json(json: Json) -> Result<firestore::Document, JsonError> {
+    let mut object = expect_object(json, "document")?;
+
+    Ok(firestore::Document {
+        name: match object.remove("name") {
+            Some(name) => expect_string(name, "name")?,
+            None => String::new(),
+        },
+        fields: match object.remove("fields") {
+            Some(fields) => fields_from_json(fields)?,
+            None => HashMap::new(),
+        },
+        create_time: object
+            .remove("createTime")
+            .map(|time| timestamp_from_json(&expect_string(time, "createTime")?))
+            .transpose()?,
+        update_time: object
+            .remove("updateTime")
+            .map(|time| timestamp_from_json(&expect_string(time, "updateTime")?))
+            .transpose()?,
+    })
+}
+
+pub(crate) fn fields_to_json(fields: &HashMap<String, firestore::Value>) -> Json {
+    Json::Object(
+        fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_json(value)))
+            .collect(),
+    )
+}
+
+pub(crate) fn fields_from_json(json: Json) -> Result<HashMap<String, firestore::Value>, JsonError> {
+    expect_object(json, "fields")?
+        .into_iter()
+        .map(|(name, value)| Ok((name, value_from_json(value)?)))
+        .collect()
+}
+
+pub(crate) fn value_to_json(value: &firestore::Value) -> Json {
+    use firestore::value::ValueType;
+
+    let value_type = match &value.value_type {
+        Some(value_type) => value_type,
+        None => return json!({}),
+    };
+
+    let (key, json) = match value_type {
+        ValueType::NullValue(_) => ("nullValue", Json::Null),
+        ValueType::BooleanValue(b) => ("booleanValue", Json::Bool(*b)),
+        ValueType::IntegerValue(i) => ("integerValue", i.to_string().into()),
+        ValueType::DoubleValue(d) => ("doubleValue", double_to_json(*d)),
+        ValueType::TimestampValue(t) => ("timestampValue", timestamp_to_json(t).into()),
+        ValueType::StringValue(s) => ("stringValue", s.clone().into()),
+        ValueType::BytesValue(b) => ("bytesValue", bytes_to_json(b).into()),
+        ValueType::ReferenceValue(r) => ("referenceValue", r.clone().into()),
+        ValueType::GeoPointValue(point) => (
+            "geoPointValue",
+            json!({
+                "latitude": double_to_json(point.latitude),
+                "longitude": double_to_json(point.longitude),
+            }),
+        ),
+        ValueType::ArrayValue(array) => (
+            "arrayValue",
+            json!({ "values": array.values.iter().map(value_to_json).collect::<Vec<_>>() }),
+        ),
+        ValueType::MapValue(map) => ("mapValue", json!({ "fields": fields_to_json(&map.fields) })),
+    };
+
+    let mut object = Map::new();
+    object.insert(key.into(), json);
+    Json::Object(object)
+}
+
+pub(crate) fn value_from_json(json: Json) -> Result<firestore::Value, JsonError> {
+    use firestore::value::ValueType;
+
+    let mut entries = expect_object(json, "value")?.into_iter();
+    let (key, json) = match (entries.next(), entries.next()) {
+        (Some(entry), None) => entry,
+        _ => {
+            return Err(JsonError::UnexpectedType {
+                field: "value",
+                expected: "an object with a single field",
+            })
+        }
+    };
+
+    let value_type = match key.as_str() {
+        "nullValue" => match json {
+            Json::Null => ValueType::NullValue(0),
+            Json::String(s) if s == "NULL_VALUE" => ValueType::NullValue(0),
+            _ => {
+                return Err(JsonError::UnexpectedType {
+                    field: "nullValue",
+                    expected: "null",
+                })
+            }
+        },
+        "booleanValue" => {
+            ValueType::BooleanValue(json.as_bool().ok_or(JsonError::UnexpectedType {
+                field: "booleanValue",
+                expected: "a boolean",
+            })?)
+        }
+        "integerValue" => ValueType::IntegerValue(integer_from_json(json, "integerValue")?),
+        "doubleValue" => ValueType::DoubleValue(double_from_json(json, "doubleValue")?),
+        "timestampValue" => ValueType::TimestampValue(timestamp_from_json(&expect_string(
+            json,
+            "timestampValue",
+        )?)?),
+        "stringValue" => ValueType::StringValue(expect_string(json, "stringValue")?),
+        "bytesValue" => {
+            ValueType::BytesValue(bytes_from_json(&expect_string(json, "bytesValue")?)?)
+        }
+        "referenceValue" => ValueType::ReferenceValue(expect_string(json, "referenceValue")?),
+        "geoPointValue" => {
+            let mut point = expect_object(json, "geoPointValue")?;
+            let mut coordinate = |field| match point.remove(field) {
+                Some(json) => double_from_json(json, field),
+                None => Ok(0.0),
+            };
+            ValueType::GeoPointValue(LatLng {
+                latitude: coordinate("latitude")?,
+                longitude: coordinate("longitude")?,
+            })
+        }
+        "arrayValue" => {
+            let values = match expect_object(json, "arrayValue")?.remove("values") {
+                Some(Json::Array(values)) => values
+                    .into_iter()
+                    .map(value_from_json)
+                    .collect::<Result<Vec<_>, _>>()?,
+                Some(_) => {
+                    return Err(JsonError::UnexpectedType {
+                        field: "values",
+                        expected: "an array",
+                    })
+                }
+                None => vec![],
+            };
+            ValueType::ArrayValue(firestore::ArrayValue { values })
+        }
+        "mapValue" => {
+            let fields = match expect_object(json, "mapValue")?.remove("fields") {
+                Some(fields) => fields_from_json(fields)?,
+                None => HashMap::new(),
+            };
+            ValueType::MapValue(firestore::MapValue { fields })
+        }
+        _ => return Err(JsonError::UnknownValueType(key)),
+    };
+
+    Ok(firestore::Value {
+        value_type: Some(value_type),
+    })
+}
+
+/// Formats a timestamp as RFC3339 in UTC, with 0, 3, 6 or 9 fractional digits.
+pub(crate) fn timestamp_to_json(timestamp: &prost_types::Timestamp) -> String {
+    let (year, month, day) = civil_from_days(timestamp.seconds.div_euclid(86_400));
+    let seconds = timestamp.seconds.rem_euclid(86_400);
+
+    let fraction = match timestamp.nanos {
+        0 => String::new(),
+        nanos if nanos % 1_000_000 == 0 => format!(".{:03}", nanos / 1_000_000),
+        nanos if nanos % 1000 == 0 => format!(".{:06}", nanos / 1000),
+        nanos => format!(".{:09}", nanos),
+    };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        fraction
+    )
+}
+
+/// Parses an RFC3339 timestamp, which may have any UTC offset.
+pub(crate) fn timestamp_from_json(timestamp: &str) -> Result<prost_types::Timestamp, JsonError> {
+    let invalid = || JsonError::InvalidTimestamp(timestamp.to_string());
+    let number = |start: usize, end: usize| {
+        timestamp
+            .get(start..end)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<i64>().ok())
+            .ok_or_else(invalid)
+    };
+
+    let separators = timestamp.get(4..5) == Some("-")
+        && timestamp.get(7..8) == Some("-")
+        && matches!(timestamp.get(10..11), Some("T") | Some("t"))
+        && timestamp.get(13..14) == Some(":")
+        && timestamp.get(16..17) == Some(":");
+    if !separators {
+        return Err(invalid());
+    }
+
+    let (year, month, day) = (number(0, 4)?, number(5, 7)? as u32, number(8, 10)? as u32);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) || hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    let mut rest = &timestamp[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 || digits > 9 {
+            return Err(invalid());
+        }
+        nanos = fraction[..digits].parse::<i32>().map_err(|_| invalid())?
+            * 10_i32.pow(9 - digits as u32);
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.get(0..1) {
+                Some("+") => 1,
+                Some("-") => -1,
+                _ => return Err(invalid()),
+            };
+            if rest.len() != 6 || rest.get(3..4) != Some(":") {
+                return Err(invalid());
+            }
+            let offset = &rest[1..];
+            let hours = offset[0..2].parse::<i64>().map_err(|_| invalid())?;
+            let minutes = offset[3..5].parse::<i64>().map_err(|_| invalid())?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+
+    Ok(prost_types::Timestamp {
+        seconds: days * 86_400 + hour * 3600 + minute * 60 + second - offset,
+        nanos,
+    })
+}
+
+pub(crate) fn bytes_to_json(bytes: &[u8]) -> String {
+    base64::encode(bytes)
+}
+
+/// Decodes standard or URL safe base64, with or without padding.
+pub(crate) fn bytes_from_json(bytes: &str) -> Result<Vec<u8>, JsonError> {
+    let config = if bytes.contains(&['-', '_'][..]) {
+        base64::URL_SAFE
+    } else {
+        base64::STANDARD
+    };
+
+    base64::decode_config(bytes, config).map_err(|_| JsonError::InvalidBytes(bytes.to_string()))
+}
+
+fn double_to_json(double: f64) -> Json {
+    if double.is_nan() {
+        "NaN".into()
+    } else if double.is_infinite() && double > 0.0 {
+        "Infinity".into()
+    } else if double.is_infinite() {
+        "-Infinity".into()
+    } else {
+        double.into()
+    }
+}
+
+fn double_from_json(json: Json, field: &'static str) -> Result<f64, JsonError> {
+    match &json {
+        Json::Number(number) => number.as_f64(),
+        Json::String(s) if s == "NaN" => Some(f64::NAN),
+        Json::String(s) if s == "Infinity" => Some(f64::INFINITY),
+        Json::String(s) if s == "-Infinity" => Some(f64::NEG_INFINITY),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or(JsonError::UnexpectedType {
+        field,
+        expected: "a number",
+    })
+}
+
+/// Parses an integer, which should be a string but may be a number.
+pub(crate) fn integer_from_json(json: Json, field: &'static str) -> Result<i64, JsonError> {
+    match json {
+        Json::String(s) => s.parse().map_err(|_| JsonError::InvalidInteger(s)),
+        Json::Number(number) => number
+            .as_i64()
+            .ok_or_else(|| JsonError::InvalidInteger(number.to_string())),
+        _ => Err(JsonError::UnexpectedType {
+            field,
+            expected: "an integer",
+        }),
+    }
+}
+
+pub(crate) fn expect_object(
+    json: Json,
+    field: &'static str,
+) -> Result<Map<String, Json>, JsonError> {
+    match json {
+        Json::Object(object) => Ok(object),
+        _ => Err(JsonError::UnexpectedType {
+            field,
+            expected: "an object",
+        }),
+    }
+}
+
+pub(crate) fn expect_string(json: Json, field: &'static str) -> Result<String, JsonError> {
+    match json {
+        Json::String(s) => Ok(s),
+        _ => Err(JsonError::UnexpectedType {
+            field,
+            expected: "a string",
+        }),
+    }
+}
+
+// Conversions between days since the unix epoch & proleptic Gregorian dates, from
+// http://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month as u32, day as u32)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value_type: firestore::value::ValueType) -> firestore::Value {
+        firestore::Value {
+            value_type: Some(value_type),
+        }
+    }
+
+    #[test]
+    fn test_value_round_trip() {
+        use firestore::value::ValueType;
+
+        let document = firestore::Document {
+            name: "projects/p/databases/(default)/documents/books/a".into(),
+            fields: maplit::hashmap! {
+                "null".to_string() => value(ValueType::NullValue(0)),
+                "integer".to_string() => value(ValueType::IntegerValue(i64::MAX)),
+                "double".to_string() => value(ValueType::DoubleValue(f64::INFINITY)),
+                "bytes".to_string() => value(ValueType::BytesValue(vec![0, 255, 7])),
+                "timestamp".to_string() => value(ValueType::TimestampValue(prost_types::Timestamp {
+                    seconds: 1_600_000_000,
+                    nanos: 120_000_000,
+                })),
+                "array".to_string() => value(ValueType::ArrayValue(firestore::ArrayValue {
+                    values: vec![value(ValueType::GeoPointValue(LatLng {
+                        latitude: 51.5,
+                        longitude: -0.1,
+                    }))],
+                })),
+                "map".to_string() => value(ValueType::MapValue(firestore::MapValue {
+                    fields: maplit::hashmap! {
+                        "reference".to_string() => value(ValueType::ReferenceValue("projects/p/databases/(default)/documents/books/b".into())),
+                    },
+                })),
+            },
+            create_time: None,
+            update_time: Some(prost_types::Timestamp {
+                seconds: 0,
+                nanos: 1,
+            }),
+        };
+
+        let json = document_to_json(&document);
+
+        insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r###"
+        {
+          "fields": {
+            "array": {
+              "arrayValue": {
+                "values": [
+                  {
+                    "geoPointValue": {
+                      "latitude": 51.5,
+                      "longitude": -0.1
+                    }
+                  }
+                ]
+              }
+            },
+            "bytes": {
+              "bytesValue": "AP8H"
+            },
+            "double": {
+              "doubleValue": "Infinity"
+            },
+            "integer": {
+              "integerValue": "9223372036854775807"
+            },
+            "map": {
+              "mapValue": {
+                "fields": {
+                  "reference": {
+                    "referenceValue": "projects/p/databases/(default)/documents/books/b"
+                  }
+                }
+              }
+            },
+            "null": {
+              "nullValue": null
+            },
+            "timestamp": {
+              "timestampValue": "2020-09-13T12:26:40.120Z"
+            }
+          },
+          "name": "projects/p/databases/(default)/documents/books/a",
+          "updateTime": "1970-01-01T00:00:00.000000001Z"
+        }
+        "###);
+        assert_eq!(document_from_json(json).unwrap(), document);
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        use firestore::value::ValueType;
+
+        assert_eq!(
+            value_from_json(json!({ "integerValue": 12 })),
+            Ok(value(ValueType::IntegerValue(12)))
+        );
+        assert_eq!(
+            value_from_json(json!({ "bytesValue": "/w==" })),
+            Ok(value(ValueType::BytesValue(vec![255])))
+        );
+        assert_eq!(
+            value_from_json(json!({ "bytesValue": "_w" })),
+            Ok(value(ValueType::BytesValue(vec![255])))
+        );
+        assert_eq!(
+            value_from_json(json!({ "arrayValue": {} })),
+            Ok(value(ValueType::ArrayValue(firestore::ArrayValue {
+                values: vec![]
+            })))
+        );
+        assert_eq!(
+            value_from_json(json!({ "stringValue": "a", "booleanValue": true })),
+            Err(JsonError::UnexpectedType {
+                field: "value",
+                expected: "an object with a single field"
+            })
+        );
+        assert_eq!(
+            value_from_json(json!({ "integerValue": "1.5" })),
+            Err(JsonError::InvalidInteger("1.5".into()))
+        );
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let timestamp = |seconds, nanos| prost_types::Timestamp { seconds, nanos };
+
+        assert_eq!(
+            timestamp_to_json(&timestamp(-1, 500_000)),
+            "1969-12-31T23:59:59.000500Z"
+        );
+        assert_eq!(
+            timestamp_to_json(&timestamp(951_782_400, 0)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            timestamp_from_json("2000-02-29T00:00:00Z"),
+            Ok(timestamp(951_782_400, 0))
+        );
+        assert_eq!(
+            timestamp_from_json("2000-02-29T01:30:00.5+01:30"),
+            Ok(timestamp(951_782_400, 500_000_000))
+        );
+        assert_eq!(
+            timestamp_from_json("1969-12-31T23:59:59.000500Z"),
+            Ok(timestamp(-1, 500_000))
+        );
+        for invalid in &[
+            "2001-02-29T00:00:00Z",
+            "2000-01-01 00:00:00Z",
+            "2000-01-01T00:00:00",
+            "2000-01-01T00:00:00.Z",
+            "2000-01-01T00:00:00.0000000001Z",
+        ] {
+            assert_eq!(
+                timestamp_from_json(invalid),
+                Err(JsonError::InvalidTimestamp(invalid.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Cross-cutting behaviour for executors.
+//!
+//! `DatabaseBuilder::layer` wraps the requests a `Database` sends to Firestore,
+//! but doesn't cover other executors.  `ExecutorExt` instead wraps an executor
+//! itself, so it works just as well inside a transaction.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use tokio::sync::Semaphore;
+
+pub use crate::database::BoxService;
+use crate::{
+    document::DocumentResponse,
+    executors::{BatchWriteExecutor, ReadExecutor, WriteExecutor},
+    operations,
+    transactions::WritePhaseExecutor,
+    values::DocumentValues,
+    ErrorDetails, FirestoreError,
+};
+
+/// The executor operations that a layer can wrap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    ListDocuments,
+    AddDocument,
+    SetDocument,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::ListDocuments => "list_documents",
+            Operation::AddDocument => "add_document",
+            Operation::SetDocument => "set_document",
+        }
+    }
+}
+
+/// Behaviour that wraps every operation run through an executor.
+pub trait ExecutorLayer: Send + Sync {
+    /// Runs `call`, the operation on the wrapped executor.
+    fn call<'a, T>(
+        &'a self,
+        operation: Operation,
+        call: BoxFuture<'a, Result<T, FirestoreError>>,
+    ) -> BoxFuture<'a, Result<T, FirestoreError>>
+    where
+        T: Send + 'a;
+}
+
+/// Adds combinators to executors.
+pub trait ExecutorExt: Sized {
+    /// Wraps every operation run through this executor in `layer`.
+    fn with_layer<L>(self, layer: L) -> Layered<Self, L>
+    where
+        L: ExecutorLayer,
+    {
+        Layered { inner: self, layer }
+    }
+
+    /// Fails operations with `DeadlineExceeded` if they take longer than
+    /// `timeout`, including any retries.
+    fn with_timeout(self, timeout: Duration) -> Layered<Self, Timeout> {
+        self.with_layer(Timeout::new(timeout))
+    }
+
+    /// Limits the number of operations running through this executor at once.
+    fn with_concurrency_limit(self, limit: usize) -> Layered<Self, ConcurrencyLimit> {
+        self.with_layer(ConcurrencyLimit::new(limit))
+    }
+}
+
+impl<E> ExecutorExt for E where E: ReadExecutor {}
+
+// The write phase of a transaction can't read, so isn't covered above.
+impl ExecutorExt for WritePhaseExecutor {}
+impl ExecutorExt for &WritePhaseExecutor {}
+
+/// An executor wrapped in an `ExecutorLayer`.
+#[derive(Clone)]
+pub struct Layered<E, L> {
+    inner: E,
+    layer: L,
+}
+
+impl<E, L> Layered<E, L> {
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<E, L> ReadExecutor for Layered<E, L>
+where
+    E: ReadExecutor,
+    L: ExecutorLayer,
+{
+    async fn list_documents(
+        &self,
+        input: operations::ListDocumentsRequest,
+    ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
+        self.layer
+            .call(Operation::ListDocuments, self.inner.list_documents(input))
+            .await
+    }
+}
+
+#[async_trait]
+impl<E, L> WriteExecutor for Layered<E, L>
+where
+    E: WriteExecutor,
+    L: ExecutorLayer,
+{
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        self.layer
+            .call(Operation::AddDocument, self.inner.add_document(input))
+            .await
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        self.layer
+            .call(Operation::SetDocument, self.inner.set_document(input))
+            .await
+    }
+}
+
+#[async_trait]
+impl<E, L> BatchWriteExecutor for Layered<E, L>
+where
+    E: BatchWriteExecutor,
+    L: ExecutorLayer,
+{
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        self.layer
+            .call(Operation::AddDocument, self.inner.add_document(input))
+            .await
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        self.layer
+            .call(Operation::SetDocument, self.inner.set_document(input))
+            .await
+    }
+}
+
+/// A layer that fails operations that take too long.
+#[derive(Clone, Copy, Debug)]
+pub struct Timeout {
+    timeout: Duration,
+}
+
+impl Timeout {
+    pub fn new(timeout: Duration) -> Self {
+        Timeout { timeout }
+    }
+}
+
+impl ExecutorLayer for Timeout {
+    fn call<'a, T>(
+        &'a self,
+        operation: Operation,
+        call: BoxFuture<'a, Result<T, FirestoreError>>,
+    ) -> BoxFuture<'a, Result<T, FirestoreError>>
+    where
+        T: Send + 'a,
+    {
+        Box::pin(async move {
+            match tokio::time::timeout(self.timeout, call).await {
+                Ok(result) => result,
+                Err(_) => Err(FirestoreError::DeadlineExceeded(Box::new(ErrorDetails {
+                    message: format!("{} timed out after {:?}", operation.name(), self.timeout),
+                    ..ErrorDetails::default()
+                }))),
+            }
+        })
+    }
+}
+
+/// A layer that limits how many operations can run at once.
+///
+/// Clones share the same limit.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(limit: usize) -> Self {
+        ConcurrencyLimit {
+            semaphore: Arc::new(Semaphore::new(limit)),
+        }
+    }
+}
+
+impl ExecutorLayer for ConcurrencyLimit {
+    fn call<'a, T>(
+        &'a self,
+        _operation: Operation,
+        call: BoxFuture<'a, Result<T, FirestoreError>>,
+    ) -> BoxFuture<'a, Result<T, FirestoreError>>
+    where
+        T: Send + 'a,
+    {
+        Box::pin(async move {
+            let _permit = self
+                .semaphore
+                .acquire()
+                .await
+                .expect("ConcurrencyLimit semaphore to never be closed");
+
+            call.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{executors::tests::TestExecutor, CollectionRef};
+
+    struct Log(Mutex<Vec<Operation>>);
+
+    impl ExecutorLayer for Log {
+        fn call<'a, T>(
+            &'a self,
+            operation: Operation,
+            call: BoxFuture<'a, Result<T, FirestoreError>>,
+        ) -> BoxFuture<'a, Result<T, FirestoreError>>
+        where
+            T: Send + 'a,
+        {
+            self.0.lock().unwrap().push(operation);
+            call
+        }
+    }
+
+    #[tokio::test]
+    async fn test_layers_see_every_operation() {
+        let executor = TestExecutor::default().with_layer(Log(Mutex::new(vec![])));
+        let books = CollectionRef::new("books");
+
+        books
+            .list_documents::<DocumentValues>()
+            .fetch_all(&executor)
+            .await
+            .ok();
+        books
+            .document("northern-lights")
+            .set(&DocumentValues::from_hashmap(Default::default()))
+            .run(&executor)
+            .await
+            .ok();
+
+        assert_eq!(
+            *executor.layer.0.lock().unwrap(),
+            vec![Operation::ListDocuments, Operation::SetDocument]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_layers_see_batch_writes() {
+        let executor = TestExecutor::default().with_layer(Log(Mutex::new(vec![])));
+
+        CollectionRef::new("books")
+            .add_document(&DocumentValues::from_hashmap(Default::default()))
+            .run_in(&executor)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *executor.layer.0.lock().unwrap(),
+            vec![Operation::AddDocument]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let timeout = Timeout::new(Duration::from_millis(10));
+
+        let result = timeout
+            .call(
+                Operation::ListDocuments,
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    Ok(())
+                }),
+            )
+            .await;
+
+        insta::assert_debug_snapshot!(result, @r###"
+        Err(
+            DeadlineExceeded(
+                ErrorDetails {
+                    message: "list_documents timed out after 10ms",
+                    error_info: None,
+                    retry_delay: None,
+                    field_violations: [],
+                    quota_violations: [],
+                    precondition_violations: [],
+                    resource_info: None,
+                    request_id: None,
+                    help_links: [],
+                },
+            ),
+        )
+        "###);
+    }
+}
@@ -2,18 +2,44 @@ mod database;
 mod document;
 mod executors;
 mod google;
+mod json;
+#[cfg(feature = "memory")]
+mod memory;
 mod paths;
 mod refs;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cassette")]
+pub mod cassette;
+pub mod layers;
 pub mod operations;
 pub mod values;
 
 pub use self::{
-    database::{Database, DatabaseBuilder, FirestoreError},
+    database::{
+        Balancing, ConnectError, Database, DatabaseBuilder, DefaultCredentialsError, ErrorDetails,
+        FirestoreError, FromEnvError, MetricsRecorder, RetryPolicy, TlsConfig, Transport,
+    },
     document::Document,
     refs::{CollectionRef, DocumentRef},
 };
 
+#[cfg(feature = "metrics")]
+pub use self::database::MetricsFacade;
+#[cfg(feature = "memory")]
+pub use self::memory::MemoryDatabase;
+
+pub mod credentials {
+    pub use super::database::auth::{
+        AccessToken, AuthorizedUserCredentials, CredentialsProvider, Impersonation,
+    };
+}
+
+pub mod error_details {
+    pub use super::database::error_details::*;
+}
+
 pub mod transactions {
     pub use super::database::transactions::*;
 }
@@ -0,0 +1,598 @@
+//! An in-memory database for testing code that uses ingle.
+//!
+//! `MemoryDatabase` implements the same executor traits as `Database`, so code
+//! that's generic over executors can be tested without the emulator or a
+//! network connection.
+
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    document::DocumentResponse,
+    executors::{BatchWriteExecutor, ReadExecutor, WriteExecutor},
+    google::firestore::v1 as firestore,
+    operations::{self, new_doc_id},
+    paths::ProjectPath,
+    values::DocumentValues,
+    ErrorDetails, FirestoreError,
+};
+
+/// An in-memory stand in for a Firestore database.
+///
+/// Documents are stored by path & returned in the same shape Firestore would
+/// return them, including their full names.  Adds fail if the document already
+/// exists, lists are ordered by document name & paginated, and sub-collections
+/// are kept separate from their parent collection.
+pub struct MemoryDatabase {
+    project_path: ProjectPath,
+    documents: Mutex<BTreeMap<String, StoredDocument>>,
+}
+
+#[derive(Clone)]
+struct StoredDocument {
+    values: DocumentValues,
+    update_time: prost_types::Timestamp,
+}
+
+impl MemoryDatabase {
+    pub fn new(project_id: impl Into<String>) -> Self {
+        MemoryDatabase {
+            project_path: ProjectPath::new(project_id.into(), "(default)".into()),
+            documents: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The number of documents in the database, across all collections.
+    pub fn len(&self) -> usize {
+        self.documents.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn create(
+        &self,
+        name: String,
+        values: DocumentValues,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let mut documents = self.documents.lock().unwrap();
+        if documents.contains_key(&name) {
+            return Err(FirestoreError::AlreadyExists(error_details(format!(
+                "Document already exists: {}",
+                name
+            ))));
+        }
+
+        documents.insert(
+            name.clone(),
+            StoredDocument {
+                values: values.clone(),
+                update_time: now(),
+            },
+        );
+
+        Ok(DocumentResponse {
+            name,
+            document: values,
+        })
+    }
+}
+
+#[async_trait]
+impl ReadExecutor for MemoryDatabase {
+    async fn list_documents(
+        &self,
+        input: operations::ListDocumentsRequest,
+    ) -> Result<operations::ListDocumentsResponse<DocumentValues>, FirestoreError> {
+        let request = input.into_firestore_request(self.project_path.clone());
+        let prefix = format!("{}/{}/", request.parent, request.collection_id);
+
+        let start = if request.page_token.is_empty() {
+            Bound::Included(prefix.clone())
+        } else if request.page_token.starts_with(&prefix) {
+            Bound::Excluded(request.page_token)
+        } else {
+            return Err(FirestoreError::InvalidArgument(error_details(
+                "Invalid page token".into(),
+            )));
+        };
+
+        let page_size = match request.page_size {
+            size if size > 0 => size as usize,
+            _ => usize::MAX,
+        };
+
+        let documents = self.documents.lock().unwrap();
+        let mut matching = documents
+            .range((start, Bound::Unbounded))
+            .take_while(|(name, _)| name.starts_with(&prefix))
+            // Documents in sub-collections have more path segments
+            .filter(|(name, _)| !name[prefix.len()..].contains('/'));
+
+        let page = matching
+            .by_ref()
+            .take(page_size)
+            .map(|(name, document)| DocumentResponse {
+                name: name.clone(),
+                document: document.values.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let next_page_token = match (matching.next(), page.last()) {
+            (Some(_), Some(last)) => Some(last.name.clone()),
+            _ => None,
+        };
+
+        Ok(operations::ListDocumentsResponse {
+            next_page_token,
+            documents: page.into_iter().map(Ok).collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl WriteExecutor for MemoryDatabase {
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let request = input.into_firestore_request(self.project_path.clone());
+        let document_id = if request.document_id.is_empty() {
+            new_doc_id()
+        } else {
+            request.document_id
+        };
+        let name = format!(
+            "{}/{}/{}",
+            request.parent, request.collection_id, document_id
+        );
+        let fields = request.document.map(|d| d.fields).unwrap_or_default();
+
+        self.create(name, DocumentValues::try_from_firestore(fields)?)
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<DocumentResponse<DocumentValues>, FirestoreError> {
+        let request = input.into_firestore_request(self.project_path.clone());
+        let document = request.document.unwrap_or_default();
+        let values = DocumentValues::try_from_firestore(document.fields)?;
+
+        let mut documents = self.documents.lock().unwrap();
+        check_precondition(
+            &document.name,
+            documents.get(&document.name),
+            request.current_document,
+        )?;
+
+        documents.insert(
+            document.name.clone(),
+            StoredDocument {
+                values: values.clone(),
+                update_time: now(),
+            },
+        );
+
+        Ok(DocumentResponse {
+            name: document.name,
+            document: values,
+        })
+    }
+}
+
+#[async_trait]
+impl BatchWriteExecutor for MemoryDatabase {
+    async fn add_document(
+        &self,
+        input: operations::AddDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        WriteExecutor::add_document(self, input).await.map(drop)
+    }
+
+    async fn set_document(
+        &self,
+        input: operations::SetDocumentRequest,
+    ) -> Result<(), FirestoreError> {
+        WriteExecutor::set_document(self, input).await.map(drop)
+    }
+}
+
+fn check_precondition(
+    name: &str,
+    existing: Option<&StoredDocument>,
+    precondition: Option<firestore::Precondition>,
+) -> Result<(), FirestoreError> {
+    use firestore::precondition::ConditionType;
+
+    match precondition.and_then(|p| p.condition_type) {
+        None => Ok(()),
+        Some(ConditionType::Exists(true)) if existing.is_none() => Err(FirestoreError::NotFound(
+            error_details(format!("No document to update: {}", name)),
+        )),
+        Some(ConditionType::Exists(false)) if existing.is_some() => {
+            Err(FirestoreError::AlreadyExists(error_details(format!(
+                "Document already exists: {}",
+                name
+            ))))
+        }
+        Some(ConditionType::Exists(_)) => Ok(()),
+        Some(ConditionType::UpdateTime(time)) => match existing {
+            Some(document) if document.update_time == time => Ok(()),
+            _ => Err(FirestoreError::FailedPrecondition(error_details(format!(
+                "The update time of {} doesn't match the precondition",
+                name
+            )))),
+        },
+    }
+}
+
+fn error_details(message: String) -> Box<ErrorDetails> {
+    Box::new(ErrorDetails {
+        message,
+        ..ErrorDetails::default()
+    })
+}
+
+fn now() -> prost_types::Timestamp {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Something fucky is happening with system time");
+
+    prost_types::Timestamp {
+        seconds: now.as_secs() as i64,
+        nanos: now.subsec_nanos() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+
+    use super::*;
+    use crate::{
+        values::{Timestamp, Value},
+        CollectionRef,
+    };
+
+    fn book(title: &str) -> DocumentValues {
+        DocumentValues::from_hashmap(hashmap! {
+            "title".to_string() => Value::String(title.to_string())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_add_and_list() {
+        let database = MemoryDatabase::new("test-project");
+        let books = CollectionRef::new("books");
+
+        let added = books
+            .add_document(&book("Northern Lights"))
+            .with_id("northern-lights")
+            .run(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            added.name,
+            "projects/test-project/databases/(default)/documents/books/northern-lights"
+        );
+
+        let listed = books
+            .list_documents::<DocumentValues>()
+            .fetch_all(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(listed, vec![added]);
+    }
+
+    #[tokio::test]
+    async fn test_add_existing_document() {
+        let database = MemoryDatabase::new("test-project");
+        let books = CollectionRef::new("books");
+
+        books
+            .add_document(&book("Northern Lights"))
+            .with_id("his-dark-materials")
+            .run(&database)
+            .await
+            .unwrap();
+
+        let error = books
+            .add_document(&book("The Subtle Knife"))
+            .with_id("his-dark-materials")
+            .run(&database)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_writes_return_errors() {
+        let database = MemoryDatabase::new("test-project");
+        let books = CollectionRef::new("books");
+
+        books
+            .add_document(&book("Northern Lights"))
+            .with_id("his-dark-materials")
+            .run_in(&database)
+            .await
+            .unwrap();
+
+        let error = books
+            .add_document(&book("The Subtle Knife"))
+            .with_id("his-dark-materials")
+            .run_in(&database)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_overwrites() {
+        let database = MemoryDatabase::new("test-project");
+        let document = CollectionRef::new("books").document("his-dark-materials");
+
+        document
+            .set(&book("Northern Lights"))
+            .run(&database)
+            .await
+            .unwrap();
+        document
+            .set(&book("The Amber Spyglass"))
+            .run(&database)
+            .await
+            .unwrap();
+
+        let listed = CollectionRef::new("books")
+            .list_documents::<DocumentValues>()
+            .fetch_all(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].document, book("The Amber Spyglass"));
+    }
+
+    #[tokio::test]
+    async fn test_set_preconditions() {
+        let database = MemoryDatabase::new("test-project");
+        let document = CollectionRef::new("books").document("his-dark-materials");
+        let set = |title| document.set(&book(title));
+
+        let error = set("Northern Lights")
+            .exists(true)
+            .run(&database)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::NotFound(_))
+        ));
+
+        set("Northern Lights")
+            .exists(false)
+            .run(&database)
+            .await
+            .unwrap();
+        let error = set("The Subtle Knife")
+            .exists(false)
+            .run(&database)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::AlreadyExists(_))
+        ));
+
+        set("The Subtle Knife")
+            .exists(true)
+            .run(&database)
+            .await
+            .unwrap();
+
+        let update_time = database
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .update_time
+            .clone();
+        let error = set("The Amber Spyglass")
+            .updated_at(Timestamp {
+                seconds: update_time.seconds - 1,
+                nandos: 0,
+            })
+            .run(&database)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            operations::OperationError::FirestoreError(FirestoreError::FailedPrecondition(_))
+        ));
+
+        let updated = set("The Amber Spyglass")
+            .updated_at(Timestamp::from_firestore(update_time))
+            .run(&database)
+            .await
+            .unwrap();
+        assert_eq!(updated.document, book("The Amber Spyglass"));
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let database = MemoryDatabase::new("test-project");
+        let books = CollectionRef::new("books");
+
+        for id in &["a", "b", "c", "d", "e"] {
+            books
+                .document(*id)
+                .set(&book(id))
+                .run(&database)
+                .await
+                .unwrap();
+        }
+
+        let first_page = books
+            .list_documents::<DocumentValues>()
+            .page_size(2)
+            .fetch_page(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(first_page.documents.len(), 2);
+
+        let all = books
+            .list_documents::<DocumentValues>()
+            .page_size(2)
+            .fetch_all(&database)
+            .await
+            .unwrap();
+
+        let mut titles = all
+            .into_iter()
+            .map(|response| response.document)
+            .collect::<Vec<_>>();
+        titles.sort_by_key(|values| format!("{:?}", values));
+
+        assert_eq!(
+            titles,
+            vec![book("a"), book("b"), book("c"), book("d"), book("e")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sub_collections_are_separate() {
+        let database = MemoryDatabase::new("test-project");
+        let northern_lights = CollectionRef::new("books").document("northern-lights");
+
+        northern_lights
+            .set(&book("Northern Lights"))
+            .run(&database)
+            .await
+            .unwrap();
+        northern_lights
+            .sub_collection("characters")
+            .add_document(&book("Lyra Belacqua"))
+            .run(&database)
+            .await
+            .unwrap();
+
+        let books = CollectionRef::new("books")
+            .list_documents::<DocumentValues>()
+            .fetch_all(&database)
+            .await
+            .unwrap();
+        let characters = northern_lights
+            .sub_collection("characters")
+            .list_documents::<DocumentValues>()
+            .fetch_all(&database)
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+        assert_eq!(characters.len(), 1);
+        assert_eq!(database.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let database = MemoryDatabase::new("test-project");
+        let books = CollectionRef::new("books");
+        books
+            .document("northern-lights")
+            .set(&book("Northern Lights"))
+            .run(&database)
+            .await
+            .unwrap();
+
+        let mut export = vec![];
+        books.export(&mut export).run(&database).await.unwrap();
+
+        let other_database = MemoryDatabase::new("other-project");
+        books
+            .document("northern-lights")
+            .set(&book("Old"))
+            .run(&other_database)
+            .await
+            .unwrap();
+        let progress = books
+            .import(export.as_slice())
+            .conflicts(operations::ConflictPolicy::Overwrite)
+            .run(&other_database)
+            .await
+            .unwrap();
+
+        assert_eq!(progress.written, 1);
+        assert_eq!(
+            books
+                .list_documents::<DocumentValues>()
+                .fetch_all(&other_database)
+                .await
+                .unwrap()[0]
+                .document,
+            book("Northern Lights")
+        );
+    }
+
+    #[test]
+    fn test_preconditions() {
+        use firestore::precondition::ConditionType;
+
+        let existing = StoredDocument {
+            values: book("Northern Lights"),
+            update_time: now(),
+        };
+        let exists = |exists| {
+            Some(firestore::Precondition {
+                condition_type: Some(ConditionType::Exists(exists)),
+            })
+        };
+
+        assert!(check_precondition("doc", None, exists(false)).is_ok());
+        assert!(check_precondition("doc", Some(&existing), exists(true)).is_ok());
+        assert!(matches!(
+            check_precondition("doc", None, exists(true)),
+            Err(FirestoreError::NotFound(_))
+        ));
+        assert!(matches!(
+            check_precondition("doc", Some(&existing), exists(false)),
+            Err(FirestoreError::AlreadyExists(_))
+        ));
+
+        let update_time = |time| {
+            Some(firestore::Precondition {
+                condition_type: Some(ConditionType::UpdateTime(time)),
+            })
+        };
+
+        assert!(check_precondition(
+            "doc",
+            Some(&existing),
+            update_time(existing.update_time.clone())
+        )
+        .is_ok());
+        assert!(matches!(
+            check_precondition("doc", Some(&existing), update_time(Default::default())),
+            Err(FirestoreError::FailedPrecondition(_))
+        ));
+    }
+}
@@ -63,13 +63,13 @@ where
         })
     }
 
-    pub async fn run_in<E>(self, executor: E)
+    pub async fn run_in<E>(self, executor: E) -> Result<(), OperationError>
     where
         E: BatchWriteExecutor,
     {
-        executor
-            .add_document(self.into_request().expect("TODO: errors"))
-            .await
+        executor.add_document(self.into_request()?).await?;
+
+        Ok(())
     }
 }
 
@@ -92,6 +92,18 @@ pub struct AddDocumentRequest {
 }
 
 impl AddDocumentRequest {
+    pub(crate) fn collection_path(&self) -> &CollectionPath {
+        &self.collection_path
+    }
+
+    pub(crate) fn document_id(&self) -> &str {
+        &self.document_id
+    }
+
+    pub(crate) fn document(&self) -> &DocumentValues {
+        &self.document
+    }
+
     pub(crate) fn into_firestore_request(
         self,
         project_path: ProjectPath,
@@ -113,14 +125,27 @@ impl AddDocumentRequest {
     }
 
     pub(crate) fn into_firestore_write(self, project_path: ProjectPath) -> firestore::Write {
+        // Like CreateDocument, a write with an explicit ID shouldn't replace
+        // an existing document.
+        let (document_id, current_document) = if self.document_id.is_empty() {
+            (new_doc_id(), None)
+        } else {
+            (
+                self.document_id,
+                Some(firestore::Precondition {
+                    condition_type: Some(firestore::precondition::ConditionType::Exists(false)),
+                }),
+            )
+        };
+
         firestore::Write {
             update_mask: None,
             update_transforms: vec![],
-            current_document: None,
+            current_document,
             operation: Some(firestore::write::Operation::Update(firestore::Document {
                 name: self
                     .collection_path
-                    .document(new_doc_id())
+                    .document(document_id)
                     .full_path(project_path),
                 fields: self.document.into_firestore(),
                 create_time: None,
@@ -130,7 +155,7 @@ impl AddDocumentRequest {
     }
 }
 
-fn new_doc_id() -> String {
+pub(crate) fn new_doc_id() -> String {
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
 
@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use futures_util::StreamExt;
+use serde_json::Value as Json;
+
+use super::OperationError;
+use crate::{
+    executors::ReadExecutor,
+    paths::{CollectionPath, DocumentPath},
+    values::DocumentValues,
+    CollectionRef,
+};
+
+impl crate::CollectionRef {
+    /// Exports the documents in this collection to `writer` as newline
+    /// delimited JSON.
+    ///
+    /// Each line is a document in the proto3 JSON mapping, along with its
+    /// path relative to the database, e.g.
+    /// `{"fields": {"title": {"stringValue": "Northern Lights"}}, "path": "books/a"}`.
+    pub fn export<W>(&self, writer: W) -> ExportOperation<W>
+    where
+        W: Write,
+    {
+        ExportOperation::new(self.path.clone(), writer)
+    }
+}
+
+#[derive(Debug)]
+#[must_use]
+pub struct ExportOperation<W> {
+    collection_path: CollectionPath,
+
+    writer: W,
+
+    sub_collections: Vec<String>,
+
+    page_size: Option<i32>,
+}
+
+impl<W> ExportOperation<W>
+where
+    W: Write,
+{
+    fn new(collection_path: CollectionPath, writer: W) -> Self {
+        ExportOperation {
+            collection_path,
+            writer,
+            sub_collections: vec![],
+            page_size: None,
+        }
+    }
+
+    /// Also exports the sub-collections with these IDs, under every exported
+    /// document at any depth.
+    ///
+    /// Sub-collections have to be named, as ingle can't list them yet.
+    pub fn sub_collections<I>(self, ids: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        ExportOperation {
+            sub_collections: ids.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    pub fn page_size(self, page_size: i32) -> Self {
+        ExportOperation {
+            page_size: Some(page_size),
+            ..self
+        }
+    }
+
+    /// Runs the export, returning the number of documents exported.
+    ///
+    /// `writer` is written to synchronously, so should usually be buffered.
+    pub async fn run<E>(self, executor: E) -> Result<u64, ExportError>
+    where
+        E: ReadExecutor,
+    {
+        let mut writer = self.writer;
+        let mut exported = 0;
+        let mut collections = vec![self.collection_path];
+
+        while let Some(collection_path) = collections.pop() {
+            let mut operation = CollectionRef {
+                path: collection_path.clone(),
+            }
+            .list_documents::<DocumentValues>();
+            if let Some(page_size) = self.page_size {
+                operation = operation.page_size(page_size);
+            }
+
+            let mut documents = Box::pin(operation.stream(&executor));
+            while let Some(document) = documents.next().await {
+                let document = document?;
+                let id = document.name.rsplit('/').next().unwrap_or_default();
+                let path = collection_path.document(id.to_string());
+
+                write_line(&mut writer, &path, &document.document)?;
+                exported += 1;
+
+                collections.extend(
+                    self.sub_collections
+                        .iter()
+                        .rev()
+                        .map(|id| path.collection(id.clone())),
+                );
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(exported)
+    }
+}
+
+fn write_line<W>(
+    writer: &mut W,
+    path: &DocumentPath,
+    document: &DocumentValues,
+) -> std::io::Result<()>
+where
+    W: Write,
+{
+    let mut json = document.to_json();
+    if let Json::Object(object) = &mut json {
+        object.insert(
+            "path".into(),
+            path.relative_path().trim_start_matches('/').into(),
+        );
+    }
+
+    serde_json::to_writer(&mut *writer, &json)?;
+    writer.write_all(b"\n")
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("Couldn't write the export: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("{0}")]
+    OperationError(#[from] OperationError),
+}
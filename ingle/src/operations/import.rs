@@ -0,0 +1,346 @@
+use std::io::BufRead;
+
+use serde_json::Value as Json;
+
+use super::OperationError;
+use crate::{
+    executors::WriteExecutor,
+    paths::{self, CollectionPath},
+    values::{DocumentValues, JsonError},
+    CollectionRef, DocumentRef, FirestoreError,
+};
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+impl crate::CollectionRef {
+    /// Imports documents into this collection from newline delimited JSON,
+    /// in the format written by `export`.
+    ///
+    /// Every document is written to the path on its line, which must be in
+    /// this collection or one of its sub-collections.
+    pub fn import<R>(&self, reader: R) -> ImportOperation<R>
+    where
+        R: BufRead,
+    {
+        ImportOperation::new(self.path.clone(), reader)
+    }
+}
+
+/// What to do when an imported document already exists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    /// Stop the import with `ImportError::AlreadyExists`.
+    Fail,
+    /// Leave the existing document alone.
+    Skip,
+    /// Replace the existing document.
+    Overwrite,
+}
+
+// Derived enum defaults need Rust 1.62.
+#[allow(clippy::derivable_impls)]
+impl Default for ConflictPolicy {
+    fn default() -> Self {
+        ConflictPolicy::Fail
+    }
+}
+
+/// How far an import has got.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImportProgress {
+    /// The number of lines that have been imported.  An interrupted import can
+    /// be resumed by passing this to `ImportOperation::skip_lines`.
+    pub lines: u64,
+    pub written: u64,
+    /// The number of documents left alone by `ConflictPolicy::Skip`.
+    pub skipped: u64,
+}
+
+type ProgressCallback = Box<dyn FnMut(&ImportProgress) + Send>;
+
+#[must_use]
+pub struct ImportOperation<R> {
+    collection_path: CollectionPath,
+
+    reader: R,
+
+    conflicts: ConflictPolicy,
+
+    batch_size: usize,
+
+    skip_lines: u64,
+
+    on_progress: Option<ProgressCallback>,
+}
+
+impl<R> ImportOperation<R>
+where
+    R: BufRead,
+{
+    fn new(collection_path: CollectionPath, reader: R) -> Self {
+        ImportOperation {
+            collection_path,
+            reader,
+            conflicts: ConflictPolicy::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            skip_lines: 0,
+            on_progress: None,
+        }
+    }
+
+    pub fn conflicts(self, conflicts: ConflictPolicy) -> Self {
+        ImportOperation { conflicts, ..self }
+    }
+
+    /// Sets how many documents are written between progress reports, 100 by
+    /// default.
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        ImportOperation {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Skips the first `lines` lines of the input, to resume an import.
+    pub fn skip_lines(self, lines: u64) -> Self {
+        ImportOperation {
+            skip_lines: lines,
+            ..self
+        }
+    }
+
+    /// Calls `on_progress` whenever documents have been written.
+    pub fn on_progress<F>(self, on_progress: F) -> Self
+    where
+        F: FnMut(&ImportProgress) + Send + 'static,
+    {
+        ImportOperation {
+            on_progress: Some(Box::new(on_progress)),
+            ..self
+        }
+    }
+
+    /// Runs the import, returning the final progress.
+    ///
+    /// `reader` is read synchronously, so should usually be buffered.  If a
+    /// document fails to import every line before it has been imported and
+    /// nothing after it has, so the last progress reported can be used to
+    /// resume.
+    pub async fn run<E>(self, executor: E) -> Result<ImportProgress, ImportError>
+    where
+        E: WriteExecutor,
+    {
+        let mut importer = Importer {
+            executor,
+            conflicts: self.conflicts,
+            progress: ImportProgress {
+                lines: self.skip_lines,
+                ..ImportProgress::default()
+            },
+            on_progress: self.on_progress,
+        };
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut line_number = self.skip_lines;
+        let mut lines = self.reader.lines();
+
+        for _ in 0..self.skip_lines {
+            if lines.next().transpose()?.is_none() {
+                return Ok(importer.progress);
+            }
+        }
+
+        while let Some(line) = lines.next().transpose()? {
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let document = ImportedDocument::parse(&line, line_number)?;
+            if !document.is_in(&self.collection_path) {
+                return Err(ImportError::OutsideCollection {
+                    line: line_number,
+                    path: document.path(),
+                });
+            }
+
+            batch.push(document);
+            if batch.len() == self.batch_size {
+                importer
+                    .write(std::mem::take(&mut batch), line_number)
+                    .await?;
+            }
+        }
+
+        importer.write(batch, line_number).await?;
+
+        Ok(importer.progress)
+    }
+}
+
+struct Importer<E> {
+    executor: E,
+    conflicts: ConflictPolicy,
+    progress: ImportProgress,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl<E> Importer<E>
+where
+    E: WriteExecutor,
+{
+    /// Writes a batch of documents read from the lines up to `last_line`.
+    async fn write(
+        &mut self,
+        batch: Vec<ImportedDocument>,
+        last_line: u64,
+    ) -> Result<(), ImportError> {
+        if batch.is_empty() {
+            self.progress.lines = last_line;
+            return Ok(());
+        }
+
+        // Documents are written one at a time, so that everything before
+        // `progress.lines` has been written & nothing after it has.
+        for document in &batch {
+            match document.write(&self.executor, self.conflicts).await {
+                Ok(()) => self.progress.written += 1,
+                Err(OperationError::FirestoreError(FirestoreError::AlreadyExists(_)))
+                    if self.conflicts == ConflictPolicy::Skip =>
+                {
+                    self.progress.skipped += 1
+                }
+                Err(error) => {
+                    self.report_progress();
+                    return Err(match error {
+                        OperationError::FirestoreError(FirestoreError::AlreadyExists(_)) => {
+                            ImportError::AlreadyExists {
+                                line: document.line,
+                                path: document.path(),
+                            }
+                        }
+                        error => error.into(),
+                    });
+                }
+            }
+            self.progress.lines = document.line;
+        }
+
+        self.progress.lines = last_line;
+        self.report_progress();
+
+        Ok(())
+    }
+
+    fn report_progress(&mut self) {
+        if let Some(on_progress) = &mut self.on_progress {
+            on_progress(&self.progress);
+        }
+    }
+}
+
+struct ImportedDocument {
+    line: u64,
+    collection_path: CollectionPath,
+    id: String,
+    values: DocumentValues,
+}
+
+impl ImportedDocument {
+    fn parse(line: &str, line_number: u64) -> Result<Self, ImportError> {
+        let invalid = |error| ImportError::InvalidDocument {
+            line: line_number,
+            error,
+        };
+
+        let mut json = serde_json::from_str::<Json>(line)
+            .map_err(|e| invalid(JsonError::InvalidJson(e.to_string())))?;
+        let path = match json
+            .as_object_mut()
+            .and_then(|object| object.remove("path"))
+        {
+            Some(Json::String(path)) => path,
+            _ => {
+                return Err(invalid(JsonError::UnexpectedType {
+                    field: "path",
+                    expected: "a string",
+                }))
+            }
+        };
+        let values = DocumentValues::from_json(json).map_err(invalid)?;
+        let (collection_path, id) =
+            paths::parse_document_path(&path).ok_or(ImportError::InvalidPath {
+                line: line_number,
+                path,
+            })?;
+
+        Ok(ImportedDocument {
+            line: line_number,
+            collection_path,
+            id,
+            values,
+        })
+    }
+
+    fn path(&self) -> String {
+        self.document_ref()
+            .path
+            .relative_path()
+            .trim_start_matches('/')
+            .to_string()
+    }
+
+    fn is_in(&self, collection_path: &CollectionPath) -> bool {
+        let collection = collection_path.relative_path();
+        let document = self.document_ref();
+        let document = document.path.relative_path();
+
+        document.starts_with(&collection) && document[collection.len()..].starts_with('/')
+    }
+
+    fn collection_ref(&self) -> CollectionRef {
+        CollectionRef {
+            path: self.collection_path.clone(),
+        }
+    }
+
+    fn document_ref(&self) -> DocumentRef {
+        self.collection_ref().document(self.id.clone())
+    }
+
+    async fn write<E>(&self, executor: E, conflicts: ConflictPolicy) -> Result<(), OperationError>
+    where
+        E: WriteExecutor,
+    {
+        match conflicts {
+            ConflictPolicy::Overwrite => self
+                .document_ref()
+                .set(&self.values)
+                .run(executor)
+                .await
+                .map(drop),
+            ConflictPolicy::Fail | ConflictPolicy::Skip => self
+                .collection_ref()
+                .add_document(&self.values)
+                .with_id(self.id.clone())
+                .run(executor)
+                .await
+                .map(drop),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("Couldn't read the import: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid document on line {line}: {error}")]
+    InvalidDocument { line: u64, error: JsonError },
+    #[error("Invalid document path {path} on line {line}")]
+    InvalidPath { line: u64, path: String },
+    #[error("Document {path} on line {line} isn't in the collection being imported")]
+    OutsideCollection { line: u64, path: String },
+    #[error("Document {path} on line {line} already exists")]
+    AlreadyExists { line: u64, path: String },
+    #[error("{0}")]
+    OperationError(#[from] OperationError),
+}
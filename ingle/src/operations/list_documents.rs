@@ -12,6 +12,7 @@ use crate::{
     paths::CollectionPath,
     paths::ProjectPath,
     values::DecodingError,
+    RetryPolicy,
 };
 
 impl crate::CollectionRef {
@@ -30,6 +31,7 @@ pub struct ListDocumentsOperation<T> {
     page_size: Option<i32>,
     page_token: Option<String>,
     max_results: Option<i32>,
+    retry_policy: Option<RetryPolicy>,
     phantom: PhantomData<fn() -> T>,
 }
 
@@ -40,6 +42,7 @@ impl<T> Clone for ListDocumentsOperation<T> {
             page_size: self.page_size,
             page_token: self.page_token.clone(),
             max_results: self.max_results,
+            retry_policy: self.retry_policy.clone(),
             phantom: PhantomData,
         }
     }
@@ -55,6 +58,7 @@ where
             page_size: None,
             page_token: None,
             max_results: None,
+            retry_policy: None,
             phantom: PhantomData,
         }
     }
@@ -73,6 +77,14 @@ where
         }
     }
 
+    /// Overrides the databases retry policy for this operation.
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy: Some(retry_policy),
+            ..self
+        }
+    }
+
     fn maximum_results(self, max_results: i32) -> Self {
         Self {
             max_results: Some(max_results),
@@ -267,6 +279,7 @@ impl<T> IntoRequest for ListDocumentsOperation<T> {
             page_size: self.page_size.unwrap_or_default(),
             page_token: self.page_token.unwrap_or_default(),
             transaction_id: None,
+            retry_policy: self.retry_policy,
         })
     }
 }
@@ -276,9 +289,30 @@ pub struct ListDocumentsRequest {
     page_size: i32,
     page_token: String,
     transaction_id: Option<Vec<u8>>,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl ListDocumentsRequest {
+    pub(crate) fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    pub(crate) fn collection_path(&self) -> &CollectionPath {
+        &self.collection_path
+    }
+
+    pub(crate) fn page_size(&self) -> i32 {
+        self.page_size
+    }
+
+    pub(crate) fn page_token(&self) -> &str {
+        &self.page_token
+    }
+
+    pub(crate) fn is_in_transaction(&self) -> bool {
+        self.transaction_id.is_some()
+    }
+
     pub(crate) fn into_firestore_request(
         self,
         project_path: ProjectPath,
@@ -1,10 +1,21 @@
 mod add_document;
+mod export;
+mod import;
 mod list_documents;
 mod set_document;
 
+#[cfg(feature = "memory")]
+pub(crate) use self::add_document::new_doc_id;
+#[cfg(feature = "cassette")]
+pub(crate) use self::set_document::Precondition;
 pub use self::{
     add_document::{AddDocumentOperation, AddDocumentRequest},
-    list_documents::{ListDocumentsOperation, ListDocumentsRequest, ListDocumentsResponse},
+    export::{ExportError, ExportOperation},
+    import::{ConflictPolicy, ImportError, ImportOperation, ImportProgress},
+    list_documents::{
+        ListDocumentsOperation, ListDocumentsPageStream, ListDocumentsRequest,
+        ListDocumentsResponse, ListDocumentsStream,
+    },
     set_document::{SetDocumentOperation, SetDocumentRequest},
 };
 
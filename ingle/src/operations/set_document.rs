@@ -7,7 +7,8 @@ use crate::{
     google::firestore::v1 as firestore,
     paths::ProjectPath,
     paths::{CollectionPath, DocumentPath},
-    values::{DocumentValues, EncodingError},
+    values::{DocumentValues, EncodingError, Timestamp},
+    RetryPolicy,
 };
 
 impl crate::DocumentRef {
@@ -26,6 +27,10 @@ pub struct SetDocumentOperation<T> {
 
     document: Result<DocumentValues, EncodingError>,
 
+    retry_policy: Option<RetryPolicy>,
+
+    precondition: Option<Precondition>,
+
     t: PhantomData<fn() -> T>,
 }
 
@@ -37,10 +42,37 @@ where
         Self {
             document_path,
             document: document.to_values(),
+            retry_policy: None,
+            precondition: None,
             t: PhantomData,
         }
     }
 
+    /// Overrides the databases retry policy for this operation.
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy: Some(retry_policy),
+            ..self
+        }
+    }
+
+    /// Only sets the document if it already exists, or if it doesn't when
+    /// `exists` is false.
+    pub fn exists(self, exists: bool) -> Self {
+        Self {
+            precondition: Some(Precondition::Exists(exists)),
+            ..self
+        }
+    }
+
+    /// Only sets the document if it was last updated at `update_time`.
+    pub fn updated_at(self, update_time: Timestamp) -> Self {
+        Self {
+            precondition: Some(Precondition::UpdateTime(update_time)),
+            ..self
+        }
+    }
+
     pub async fn run<E>(self, executor: E) -> Result<DocumentResponse<T>, OperationError>
     where
         E: WriteExecutor,
@@ -52,6 +84,15 @@ where
             document: T::from_values(response.document)?,
         })
     }
+
+    pub async fn run_in<E>(self, executor: E) -> Result<(), OperationError>
+    where
+        E: BatchWriteExecutor,
+    {
+        executor.set_document(self.into_request()?).await?;
+
+        Ok(())
+    }
 }
 
 impl<T> IntoRequest for SetDocumentOperation<T> {
@@ -61,16 +102,57 @@ impl<T> IntoRequest for SetDocumentOperation<T> {
         Ok(SetDocumentRequest {
             document_path: self.document_path,
             document: self.document?,
+            retry_policy: self.retry_policy,
+            precondition: self.precondition,
         })
     }
 }
 
+/// A condition the existing document has to meet for a set to happen.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Precondition {
+    Exists(bool),
+    UpdateTime(Timestamp),
+}
+
+impl Precondition {
+    fn into_firestore(self) -> firestore::Precondition {
+        use firestore::precondition::ConditionType;
+
+        firestore::Precondition {
+            condition_type: Some(match self {
+                Precondition::Exists(exists) => ConditionType::Exists(exists),
+                Precondition::UpdateTime(time) => ConditionType::UpdateTime(time.into_firestore()),
+            }),
+        }
+    }
+}
+
 pub struct SetDocumentRequest {
     document_path: DocumentPath,
     document: DocumentValues,
+    retry_policy: Option<RetryPolicy>,
+    precondition: Option<Precondition>,
 }
 
 impl SetDocumentRequest {
+    pub(crate) fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    pub(crate) fn document_path(&self) -> &DocumentPath {
+        &self.document_path
+    }
+
+    pub(crate) fn document(&self) -> &DocumentValues {
+        &self.document
+    }
+
+    #[cfg(feature = "cassette")]
+    pub(crate) fn precondition(&self) -> Option<&Precondition> {
+        self.precondition.as_ref()
+    }
+
     pub(crate) fn into_firestore_request(
         self,
         project_path: ProjectPath,
@@ -84,7 +166,7 @@ impl SetDocumentRequest {
             }),
             mask: None,
             update_mask: None,
-            current_document: None,
+            current_document: self.precondition.map(Precondition::into_firestore),
         }
     }
 
@@ -92,7 +174,7 @@ impl SetDocumentRequest {
         firestore::Write {
             update_mask: None,
             update_transforms: vec![],
-            current_document: None,
+            current_document: self.precondition.map(Precondition::into_firestore),
             operation: Some(firestore::write::Operation::Update(firestore::Document {
                 name: self.document_path.full_path(project_path),
                 fields: self.document.into_firestore(),
@@ -102,4 +184,3 @@ impl SetDocumentRequest {
         }
     }
 }
-
@@ -30,6 +30,15 @@ impl CollectionPath {
         DocumentPath { path }
     }
 
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The path of this collection relative to the database, e.g. `/books`.
+    pub(crate) fn relative_path(&self) -> String {
+        format!("{}/{}", self.parent.as_deref().unwrap_or_default(), self.id)
+    }
+
     pub fn parent_and_collection_id(self, project_path: ProjectPath) -> (String, String) {
         let documents_part = "/documents";
         let parent_len = self.parent.as_ref().map(String::len).unwrap_or_default();
@@ -58,6 +67,11 @@ impl DocumentPath {
         }
     }
 
+    /// The path of this document relative to the database, e.g. `/books/a`.
+    pub(crate) fn relative_path(&self) -> &str {
+        &self.path
+    }
+
     pub(crate) fn full_path(self, project_path: ProjectPath) -> String {
         let documents_part = "/documents";
 
@@ -71,6 +85,29 @@ impl DocumentPath {
     }
 }
 
+/// Parses a document path relative to the database, e.g. `books/a`, into the
+/// path of its collection & its ID.
+pub(crate) fn parse_document_path(path: &str) -> Option<(CollectionPath, String)> {
+    let mut segments = path.strip_prefix('/').unwrap_or(path).split('/');
+    let mut collection = CollectionPath::new(non_empty(segments.next())?);
+
+    loop {
+        let id = non_empty(segments.next())?;
+        match segments.next() {
+            None => return Some((collection, id)),
+            Some(collection_id) => {
+                collection = collection
+                    .document(id)
+                    .collection(non_empty(Some(collection_id))?);
+            }
+        }
+    }
+}
+
+fn non_empty(segment: Option<&str>) -> Option<String> {
+    segment.filter(|s| !s.is_empty()).map(str::to_string)
+}
+
 #[derive(Clone)]
 pub struct ProjectPath {
     path: String,
@@ -86,6 +123,14 @@ impl ProjectPath {
     pub(crate) fn database_path(&self) -> &str {
         &self.path
     }
+
+    pub(crate) fn project_id(&self) -> &str {
+        self.path.split('/').nth(1).unwrap_or_default()
+    }
+
+    pub(crate) fn database_id(&self) -> &str {
+        self.path.split('/').nth(3).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -105,6 +150,24 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_document_path() {
+        let (collection, id) =
+            parse_document_path("books/Northern Lights/characters/Lyra").unwrap();
+        assert_eq!(
+            collection.document(id).path,
+            "/books/Northern Lights/characters/Lyra"
+        );
+
+        let (collection, id) = parse_document_path("/books/a").unwrap();
+        assert_eq!(collection.document(id).path, "/books/a");
+
+        assert!(parse_document_path("books").is_none());
+        assert!(parse_document_path("books/a/characters").is_none());
+        assert!(parse_document_path("books//characters/b").is_none());
+        assert!(parse_document_path("").is_none());
+    }
+
     #[test]
     fn test_nested_collection_path() {
         insta::assert_debug_snapshot!(
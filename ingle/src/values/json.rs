@@ -0,0 +1,333 @@
+//! Conversions between values & JSON.
+//!
+//! The proto3 JSON mapping is the shape the Firestore REST API uses, and
+//! round trips every value exactly.  Plain JSON maps values to the nearest
+//! natural JSON type instead, which is easier to work with but loses type
+//! information: timestamps, bytes & references all become strings.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value as Json};
+
+use super::{DocumentValues, JsonError, Value};
+use crate::json::{self as proto_json, bytes_to_json, timestamp_to_json};
+
+impl DocumentValues {
+    /// Encodes these values as a document in the proto3 JSON mapping, e.g.
+    /// `{"fields": {"x": {"integerValue": "1"}}}`.
+    pub fn to_json(&self) -> Json {
+        let mut document = Map::new();
+        document.insert(
+            "fields".into(),
+            proto_json::fields_to_json(&self.clone().into_firestore()),
+        );
+        Json::Object(document)
+    }
+
+    /// Decodes the fields of a document in the proto3 JSON mapping.
+    ///
+    /// Any name or timestamps on the document are ignored.
+    pub fn from_json(json: Json) -> Result<Self, JsonError> {
+        let document = proto_json::document_from_json(json)?;
+        Ok(DocumentValues::try_from_firestore(document.fields)?)
+    }
+
+    /// Like `to_json`, but as a string.
+    pub fn to_json_string(&self) -> String {
+        self.to_json().to_string()
+    }
+
+    /// Like `from_json`, but from a string.
+    pub fn from_json_str(json: &str) -> Result<Self, JsonError> {
+        DocumentValues::from_json(parse(json)?)
+    }
+
+    /// Encodes these values as a plain JSON object.
+    pub fn to_plain_json(&self) -> Json {
+        Json::Object(
+            self.0
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_plain_json()))
+                .collect(),
+        )
+    }
+
+    /// Decodes a plain JSON object.
+    pub fn from_plain_json(json: Json) -> Result<Self, JsonError> {
+        Ok(DocumentValues(
+            proto_json::expect_object(json, "document")?
+                .into_iter()
+                .map(|(name, value)| (name, Value::from_plain_json(value)))
+                .collect(),
+        ))
+    }
+}
+
+impl Value {
+    /// Encodes this value in the proto3 JSON mapping, e.g.
+    /// `{"integerValue": "1"}`.
+    pub fn to_json(&self) -> Json {
+        proto_json::value_to_json(&self.clone().into_firestore())
+    }
+
+    /// Decodes a value in the proto3 JSON mapping.
+    pub fn from_json(json: Json) -> Result<Self, JsonError> {
+        Ok(Value::try_from_firestore(proto_json::value_from_json(
+            json,
+        )?)?)
+    }
+
+    /// Like `to_json`, but as a string.
+    pub fn to_json_string(&self) -> String {
+        self.to_json().to_string()
+    }
+
+    /// Like `from_json`, but from a string.
+    pub fn from_json_str(json: &str) -> Result<Self, JsonError> {
+        Value::from_json(parse(json)?)
+    }
+
+    /// Encodes this value as the nearest plain JSON type.
+    ///
+    /// Timestamps become RFC3339 strings, bytes become base64 strings,
+    /// references become their document name and geopoints become objects
+    /// with a `latitude` & `longitude`.  Doubles that JSON can't represent
+    /// become `"NaN"`, `"Infinity"` or `"-Infinity"`.
+    pub fn to_plain_json(&self) -> Json {
+        match self {
+            Value::Null => Json::Null,
+            Value::Boolean(b) => Json::Bool(*b),
+            Value::Integer(i) => Json::from(*i),
+            Value::Double(d) => plain_double(*d),
+            Value::Timestamp(t) => timestamp_to_json(&t.clone().into_firestore()).into(),
+            Value::String(s) => s.clone().into(),
+            Value::Bytes(b) => bytes_to_json(b).into(),
+            Value::DocumentReference(r) => r.clone().into(),
+            Value::GeoPoint(p) => {
+                let mut point = Map::new();
+                point.insert("latitude".into(), plain_double(p.latitude));
+                point.insert("longitude".into(), plain_double(p.longitude));
+                Json::Object(point)
+            }
+            Value::Array(values) => values.iter().map(Value::to_plain_json).collect(),
+            Value::Map(fields) => Json::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_plain_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Decodes a plain JSON value.
+    ///
+    /// Whole numbers that fit in an `i64` become integers & other numbers
+    /// become doubles.  Strings are always decoded as strings and objects as
+    /// maps.
+    pub fn from_plain_json(json: Json) -> Self {
+        match json {
+            Json::Null => Value::Null,
+            Json::Bool(b) => Value::Boolean(b),
+            Json::Number(n) => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => Value::Double(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Json::String(s) => Value::String(s),
+            Json::Array(values) => {
+                Value::Array(values.into_iter().map(Value::from_plain_json).collect())
+            }
+            Json::Object(fields) => Value::Map(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::from_plain_json(value)))
+                    .collect::<HashMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+fn plain_double(double: f64) -> Json {
+    match Number::from_f64(double) {
+        Some(number) => Json::Number(number),
+        None if double.is_nan() => "NaN".into(),
+        None if double > 0.0 => "Infinity".into(),
+        None => "-Infinity".into(),
+    }
+}
+
+fn parse(json: &str) -> Result<Json, JsonError> {
+    serde_json::from_str(json).map_err(|e| JsonError::InvalidJson(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::values::{LatLng, Timestamp};
+
+    fn every_value() -> DocumentValues {
+        DocumentValues::from_hashmap(maplit::hashmap! {
+            "null".to_string() => Value::Null,
+            "boolean".to_string() => Value::Boolean(true),
+            "integer".to_string() => Value::Integer(-7),
+            "double".to_string() => Value::Double(f64::NEG_INFINITY),
+            "timestamp".to_string() => Value::Timestamp(Timestamp {
+                seconds: 1_628_000_000,
+                nandos: 123_456_000,
+            }),
+            "string".to_string() => Value::String("Lyra".into()),
+            "bytes".to_string() => Value::Bytes(b"alethiometer".to_vec()),
+            "reference".to_string() => Value::DocumentReference(
+                "projects/p/databases/(default)/documents/books/northern-lights".into(),
+            ),
+            "geopoint".to_string() => Value::GeoPoint(LatLng {
+                latitude: 51.75,
+                longitude: -1.25,
+            }),
+            "array".to_string() => Value::Array(vec![Value::Integer(1), Value::Double(1.5)]),
+            "map".to_string() => Value::Map(maplit::hashmap! {
+                "nested".to_string() => Value::Map(maplit::hashmap! {
+                    "empty".to_string() => Value::Array(vec![]),
+                }),
+            }),
+        })
+    }
+
+    #[test]
+    fn test_proto_json_round_trip() {
+        let values = every_value();
+
+        let json = values.to_json();
+
+        insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r###"
+        {
+          "fields": {
+            "array": {
+              "arrayValue": {
+                "values": [
+                  {
+                    "integerValue": "1"
+                  },
+                  {
+                    "doubleValue": 1.5
+                  }
+                ]
+              }
+            },
+            "boolean": {
+              "booleanValue": true
+            },
+            "bytes": {
+              "bytesValue": "YWxldGhpb21ldGVy"
+            },
+            "double": {
+              "doubleValue": "-Infinity"
+            },
+            "geopoint": {
+              "geoPointValue": {
+                "latitude": 51.75,
+                "longitude": -1.25
+              }
+            },
+            "integer": {
+              "integerValue": "-7"
+            },
+            "map": {
+              "mapValue": {
+                "fields": {
+                  "nested": {
+                    "mapValue": {
+                      "fields": {
+                        "empty": {
+                          "arrayValue": {
+                            "values": []
+                          }
+                        }
+                      }
+                    }
+                  }
+                }
+              }
+            },
+            "null": {
+              "nullValue": null
+            },
+            "reference": {
+              "referenceValue": "projects/p/databases/(default)/documents/books/northern-lights"
+            },
+            "string": {
+              "stringValue": "Lyra"
+            },
+            "timestamp": {
+              "timestampValue": "2021-08-03T14:13:20.123456Z"
+            }
+          }
+        }
+        "###);
+        assert_eq!(DocumentValues::from_json(json), Ok(values.clone()));
+        assert_eq!(
+            DocumentValues::from_json_str(&values.to_json_string()),
+            Ok(values)
+        );
+    }
+
+    #[test]
+    fn test_nan_round_trip() {
+        let json = Value::Double(f64::NAN).to_json_string();
+
+        assert_eq!(json, r#"{"doubleValue":"NaN"}"#);
+        assert!(matches!(
+            Value::from_json_str(&json),
+            Ok(Value::Double(d)) if d.is_nan()
+        ));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        assert_eq!(
+            DocumentValues::from_json_str(r#"{"fields": {"x": {"integerValue": "one"}}}"#),
+            Err(JsonError::InvalidInteger("one".into()))
+        );
+        assert_eq!(
+            Value::from_json(json!({ "fooValue": 1 })),
+            Err(JsonError::UnknownValueType("fooValue".into()))
+        );
+        assert!(matches!(
+            DocumentValues::from_json_str("{"),
+            Err(JsonError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_plain_json() {
+        let json = every_value().to_plain_json();
+
+        assert_eq!(
+            json,
+            json!({
+                "null": null,
+                "boolean": true,
+                "integer": -7,
+                "double": "-Infinity",
+                "timestamp": "2021-08-03T14:13:20.123456Z",
+                "string": "Lyra",
+                "bytes": "YWxldGhpb21ldGVy",
+                "reference": "projects/p/databases/(default)/documents/books/northern-lights",
+                "geopoint": { "latitude": 51.75, "longitude": -1.25 },
+                "array": [1, 1.5],
+                "map": { "nested": { "empty": [] } },
+            })
+        );
+        assert_eq!(
+            DocumentValues::from_plain_json(
+                json!({ "pages": 350, "rating": 4.5, "tags": ["fantasy"] })
+            ),
+            Ok(DocumentValues::from_hashmap(maplit::hashmap! {
+                "pages".to_string() => Value::Integer(350),
+                "rating".to_string() => Value::Double(4.5),
+                "tags".to_string() => Value::Array(vec![Value::String("fantasy".into())]),
+            }))
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 
-use crate::google::firestore::v1 as firestore;
+use crate::google::{firestore::v1 as firestore, r#type};
+
+mod json;
+
+pub use crate::json::JsonError;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct DocumentValues(HashMap<String, Value>);
@@ -54,6 +58,22 @@ pub struct Timestamp {
     pub nandos: i32,
 }
 
+impl Timestamp {
+    pub(crate) fn from_firestore(timestamp: prost_types::Timestamp) -> Self {
+        Timestamp {
+            seconds: timestamp.seconds,
+            nandos: timestamp.nanos,
+        }
+    }
+
+    pub(crate) fn into_firestore(self) -> prost_types::Timestamp {
+        prost_types::Timestamp {
+            seconds: self.seconds,
+            nanos: self.nandos,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct LatLng {
     pub latitude: f64,
@@ -69,11 +89,14 @@ impl Value {
             Value::Boolean(b) => ValueType::BooleanValue(b),
             Value::Integer(v) => ValueType::IntegerValue(v),
             Value::Double(v) => ValueType::DoubleValue(v),
-            Value::Timestamp(_) => todo!(),
+            Value::Timestamp(t) => ValueType::TimestampValue(t.into_firestore()),
             Value::String(s) => ValueType::StringValue(s),
             Value::Bytes(b) => ValueType::BytesValue(b),
-            Value::DocumentReference(_) => todo!(),
-            Value::GeoPoint(_) => todo!(),
+            Value::DocumentReference(r) => ValueType::ReferenceValue(r),
+            Value::GeoPoint(p) => ValueType::GeoPointValue(r#type::LatLng {
+                latitude: p.latitude,
+                longitude: p.longitude,
+            }),
             Value::Array(v) => ValueType::ArrayValue(firestore::ArrayValue {
                 values: v.into_iter().map(Value::into_firestore).collect(),
             }),
@@ -100,11 +123,14 @@ impl Value {
             ValueType::BooleanValue(b) => Value::Boolean(b),
             ValueType::IntegerValue(v) => Value::Integer(v),
             ValueType::DoubleValue(v) => Value::Double(v),
-            ValueType::TimestampValue(_) => todo!(),
+            ValueType::TimestampValue(t) => Value::Timestamp(Timestamp::from_firestore(t)),
             ValueType::StringValue(s) => Value::String(s),
             ValueType::BytesValue(b) => Value::Bytes(b),
-            ValueType::ReferenceValue(_) => todo!(),
-            ValueType::GeoPointValue(_) => todo!(),
+            ValueType::ReferenceValue(r) => Value::DocumentReference(r),
+            ValueType::GeoPointValue(p) => Value::GeoPoint(LatLng {
+                latitude: p.latitude,
+                longitude: p.longitude,
+            }),
             ValueType::ArrayValue(firestore::ArrayValue { values }) => Value::Array(
                 values
                     .into_iter()
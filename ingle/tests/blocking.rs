@@ -0,0 +1,89 @@
+#![cfg(feature = "blocking")]
+
+use ingle::{
+    blocking::{BlockingDatabase, ReadPhaseExecutor},
+    values::{DocumentValues, Value},
+    CollectionRef, DatabaseBuilder,
+};
+use ingle_testserver::TestServer;
+
+fn book(title: &str) -> DocumentValues {
+    DocumentValues::from_hashmap(maplit::hashmap! {
+        "title".to_string() => Value::String(title.to_string())
+    })
+}
+
+/// Starts a test server on its own runtime, which has to outlive the server.
+fn start_server() -> (tokio::runtime::Runtime, TestServer) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(TestServer::start()).unwrap();
+
+    (runtime, server)
+}
+
+fn connect(server: &TestServer) -> BlockingDatabase {
+    BlockingDatabase::connect(
+        DatabaseBuilder::new("test-project")
+            .http_endpoint(&server.host())
+            .emulator_owner_credentials(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_adding_and_listing_documents() {
+    let (_runtime, server) = start_server();
+    let database = connect(&server);
+    let books = CollectionRef::new("books");
+
+    database
+        .set(
+            books
+                .document("northern-lights")
+                .set(&book("Northern Lights")),
+        )
+        .unwrap();
+    database
+        .add(books.add_document(&book("The Subtle Knife")))
+        .unwrap();
+
+    let titles = database
+        .list(books.list_documents::<DocumentValues>().page_size(1))
+        .map(|document| document.unwrap().document.into_hashmap()["title"].clone())
+        .collect::<Vec<_>>();
+
+    assert_eq!(titles.len(), 2);
+    assert!(titles.contains(&Value::String("Northern Lights".into())));
+}
+
+#[test]
+fn test_transactions() {
+    let (_runtime, server) = start_server();
+    let database = connect(&server);
+
+    let outcome = database
+        .transaction()
+        .run(|tx: ReadPhaseExecutor| {
+            let books = CollectionRef::new("books");
+            let existing = tx.list(books.list_documents::<DocumentValues>()).count();
+
+            tx.finish_reads()
+                .add(books.add_document(&book("The Amber Spyglass")))
+                .unwrap();
+
+            existing
+        })
+        .unwrap();
+    assert_eq!(outcome.result, 0);
+
+    let count = database
+        .transaction()
+        .read_only()
+        .run(|tx| {
+            tx.list(CollectionRef::new("books").list_documents::<DocumentValues>())
+                .count()
+        })
+        .unwrap()
+        .result;
+    assert_eq!(count, 1);
+}
@@ -1,25 +1,50 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
 use ingle::{
-    transactions::{ReadOnlyExecutor, ReadPhaseExecutor},
+    layers::{BoxService, ExecutorExt},
+    operations::{ConflictPolicy, ImportError, ImportProgress, OperationError},
+    transactions::{ReadOnlyExecutor, ReadPhaseExecutor, TransactionError},
     values::{DocumentValues, Value},
-    CollectionRef, DatabaseBuilder,
+    CollectionRef, Database, DatabaseBuilder, DocumentRef, FirestoreError, MetricsRecorder,
 };
+use ingle_testserver::TestServer;
+use tokio::{sync::Notify, time::Instant};
+use tonic::{body::BoxBody, codegen::http::Request};
+use tower::ServiceExt;
 
-#[tokio::test]
-async fn test_adding_and_listing_documents() {
-    let database = DatabaseBuilder::new(std::env::var("GOOGLE_PROJECT").unwrap())
-        .auth_token(std::env::var("GOOGLE_TOKEN").unwrap())
+async fn connect() -> (TestServer, Database) {
+    let server = TestServer::start().await.unwrap();
+
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
         .connect()
         .await
         .unwrap();
 
-    let document = DocumentValues::from_hashmap(maplit::hashmap! {
+    (server, database)
+}
+
+fn test_document() -> DocumentValues {
+    DocumentValues::from_hashmap(maplit::hashmap! {
         "Test".to_string() => Value::Boolean(true)
-    });
+    })
+}
+
+#[tokio::test]
+async fn test_adding_and_listing_documents() {
+    let (_server, database) = connect().await;
 
     let collection = CollectionRef::new("books");
 
-    collection
-        .add_document(&document)
+    let added = collection
+        .add_document(&test_document())
         .run(&database)
         .await
         .unwrap();
@@ -30,14 +55,49 @@ async fn test_adding_and_listing_documents() {
         .await
         .unwrap();
 
-    assert!(!documents.is_empty());
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0].name, added.name);
+    assert_eq!(documents[0].document, test_document());
+}
+
+#[tokio::test]
+async fn test_set_preconditions() {
+    let (_server, database) = connect().await;
+
+    let document = CollectionRef::new("books").document("northern-lights");
+
+    let error = document
+        .set(&test_document())
+        .exists(true)
+        .run(&database)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        OperationError::FirestoreError(FirestoreError::NotFound(_))
+    ));
+
+    document
+        .set(&test_document())
+        .exists(false)
+        .run(&database)
+        .await
+        .unwrap();
+    document
+        .set(&test_document())
+        .exists(true)
+        .run(&database)
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
 async fn test_read_only_transactions() {
-    let database = DatabaseBuilder::new(std::env::var("GOOGLE_PROJECT").unwrap())
-        .auth_token(std::env::var("GOOGLE_TOKEN").unwrap())
-        .connect()
+    let (_server, database) = connect().await;
+
+    CollectionRef::new("books")
+        .add_document(&test_document())
+        .run(&database)
         .await
         .unwrap();
 
@@ -61,19 +121,12 @@ async fn test_read_only_transactions() {
 
 #[tokio::test]
 async fn test_read_write_transactions() {
-    let database = DatabaseBuilder::new(std::env::var("GOOGLE_PROJECT").unwrap())
-        .auth_token(std::env::var("GOOGLE_TOKEN").unwrap())
-        .connect()
-        .await
-        .unwrap();
-
-    println!("Connected");
+    let (_server, database) = connect().await;
 
     database
         .transaction()
         .read_write()
         .run(|tx: ReadPhaseExecutor| async move {
-            println!("In transaction");
             let collection = CollectionRef::new("books");
 
             let documents = collection
@@ -82,22 +135,701 @@ async fn test_read_write_transactions() {
                 .await
                 .unwrap();
 
-            println!("Got documents: {:?}", documents);
+            assert!(documents.is_empty());
 
-            //assert!(!documents.is_empty());
+            let tx = tx.finish_reads();
+
+            collection
+                .add_document(&test_document())
+                .run_in(&tx)
+                .await
+                .unwrap();
+        })
+        .await
+        .unwrap();
+
+    let documents = CollectionRef::new("books")
+        .list_documents::<DocumentValues>()
+        .fetch_all(&database)
+        .await
+        .unwrap();
+
+    assert_eq!(documents.len(), 1);
+}
+
+#[tokio::test]
+async fn test_adding_documents_with_ids_in_transactions() {
+    let (_server, database) = connect().await;
+
+    let add_lyra = || {
+        database
+            .transaction()
+            .max_retries(0)
+            .run(|tx: ReadPhaseExecutor| async move {
+                let tx = tx.finish_reads();
+                CollectionRef::new("characters")
+                    .add_document(&test_document())
+                    .with_id("lyra")
+                    .run_in(&tx)
+                    .await
+                    .unwrap();
+            })
+    };
+
+    add_lyra().await.unwrap();
+
+    let documents = CollectionRef::new("characters")
+        .list_documents::<DocumentValues>()
+        .fetch_all(&database)
+        .await
+        .unwrap();
+    assert_eq!(documents.len(), 1);
+    assert!(documents[0].name.ends_with("/documents/characters/lyra"));
+
+    assert!(matches!(
+        add_lyra().await,
+        Err(TransactionError::CouldNotCommitTransaction(
+            FirestoreError::AlreadyExists(_)
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn test_layers_in_transactions() {
+    let (_server, database) = connect().await;
+
+    database
+        .transaction()
+        .run(|tx: ReadPhaseExecutor| async move {
+            let tx = tx.finish_reads().with_timeout(Duration::from_secs(5));
+
+            CollectionRef::new("books")
+                .add_document(&test_document())
+                .run_in(&tx)
+                .await
+                .unwrap();
+        })
+        .await
+        .unwrap();
+
+    let documents = CollectionRef::new("books")
+        .list_documents::<DocumentValues>()
+        .fetch_all(&database)
+        .await
+        .unwrap();
+
+    assert_eq!(documents.len(), 1);
+}
+
+#[tokio::test]
+async fn test_transactions_lock_documents_they_read() {
+    let (_server, database) = connect().await;
+    let document = CollectionRef::new("books").document("northern-lights");
+
+    document.set(&test_document()).run(&database).await.unwrap();
+
+    let outside = database.clone();
+    database
+        .transaction()
+        .read_write()
+        .run(move |tx: ReadPhaseExecutor| {
+            let outside = outside.clone();
+            async move {
+                CollectionRef::new("books")
+                    .list_documents::<DocumentValues>()
+                    .fetch_all(&tx)
+                    .await
+                    .unwrap();
+
+                let error = CollectionRef::new("books")
+                    .document("northern-lights")
+                    .set(&test_document())
+                    .run(&outside)
+                    .await
+                    .unwrap_err();
+
+                assert!(matches!(
+                    error,
+                    OperationError::FirestoreError(FirestoreError::Aborted(_))
+                ));
+            }
+        })
+        .await
+        .unwrap();
+
+    // The locks are released once the transaction commits
+    document.set(&test_document()).run(&database).await.unwrap();
+}
+
+/// Writes `document` from outside of any transaction, waiting for any
+/// transactions that have locked it to roll back.
+async fn wait_for_rollback(document: &DocumentRef, database: &Database) {
+    for _ in 0..100 {
+        match document.set(&test_document()).run(database).await {
+            Err(OperationError::FirestoreError(FirestoreError::Aborted(_))) => {
+                tokio::time::sleep(Duration::from_millis(10)).await
+            }
+            result => return result.map(drop).unwrap(),
+        }
+    }
+    panic!("The transaction was never rolled back");
+}
+
+async fn lock_books(tx: &ReadPhaseExecutor) {
+    CollectionRef::new("books")
+        .list_documents::<DocumentValues>()
+        .fetch_all(tx)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_transaction_timeouts() {
+    let (_server, database) = connect().await;
+    let document = CollectionRef::new("books").document("northern-lights");
+
+    document.set(&test_document()).run(&database).await.unwrap();
+
+    let result = database
+        .transaction()
+        .timeout(Duration::from_millis(50))
+        .run(|tx: ReadPhaseExecutor| async move {
+            lock_books(&tx).await;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        })
+        .await;
+
+    assert!(matches!(result, Err(TransactionError::TimedOut)));
+
+    wait_for_rollback(&document, &database).await;
+}
+
+#[tokio::test]
+async fn test_transactions_dont_wait_for_hung_rollbacks() {
+    let server = TestServer::start().await.unwrap();
+
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
+        .layer(tower::layer::layer_fn(|inner: BoxService| {
+            BoxService::new(tower::service_fn(move |request: Request<BoxBody>| {
+                let inner = inner.clone();
+                async move {
+                    if request.uri().path().ends_with("/Rollback") {
+                        std::future::pending::<()>().await;
+                    }
+                    inner.oneshot(request).await
+                }
+            }))
+        }))
+        .connect()
+        .await
+        .unwrap();
+
+    let started = Instant::now();
+    let result = database
+        .transaction()
+        .timeout(Duration::from_millis(50))
+        .run(|_tx: ReadPhaseExecutor| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        })
+        .await;
+    assert!(matches!(result, Err(TransactionError::TimedOut)));
+    assert!(started.elapsed() < Duration::from_millis(500));
+
+    // Other rollbacks are waited for, but not forever
+    let started = Instant::now();
+    let result = database
+        .transaction()
+        .max_retries(0)
+        .run(|tx: ReadPhaseExecutor| async move {
+            tx.finish_reads().rollback().await;
+        })
+        .await;
+    assert!(matches!(result, Err(TransactionError::RollbackRequested)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_transaction_timeouts_include_begin_and_commit() {
+    let server = TestServer::start().await.unwrap();
+
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
+        .layer(tower::layer::layer_fn(|inner: BoxService| {
+            BoxService::new(inner.and_then(|response| async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(response)
+            }))
+        }))
+        .connect()
+        .await
+        .unwrap();
+
+    let result = database
+        .transaction()
+        .read_write()
+        .timeout(Duration::from_millis(150))
+        .run(|_tx: ReadPhaseExecutor| async {})
+        .await;
+    assert!(matches!(result, Err(TransactionError::TimedOut)));
+
+    let result = database
+        .transaction()
+        .read_only()
+        .timeout(Duration::from_millis(150))
+        .run(|_tx: ReadOnlyExecutor| async {})
+        .await;
+    assert!(matches!(result, Err(TransactionError::TimedOut)));
+}
+
+#[tokio::test]
+async fn test_transactions_roll_back_on_panic() {
+    let (_server, database) = connect().await;
+    let document = CollectionRef::new("books").document("northern-lights");
+
+    document.set(&test_document()).run(&database).await.unwrap();
+
+    let transaction = tokio::spawn({
+        let database = database.clone();
+        async move {
+            database
+                .transaction()
+                .run(|tx: ReadPhaseExecutor| async move {
+                    lock_books(&tx).await;
+                    panic!("Oh no");
+                })
+                .await
+        }
+    });
+
+    assert!(transaction.await.unwrap_err().is_panic());
+
+    wait_for_rollback(&document, &database).await;
+}
+
+#[tokio::test]
+async fn test_transactions_roll_back_when_dropped() {
+    let (_server, database) = connect().await;
+    let document = CollectionRef::new("books").document("northern-lights");
+    let locked = Arc::new(Notify::new());
+
+    document.set(&test_document()).run(&database).await.unwrap();
+
+    let transaction = tokio::spawn({
+        let database = database.clone();
+        let locked = locked.clone();
+        async move {
+            database
+                .transaction()
+                .run(move |tx: ReadPhaseExecutor| {
+                    let locked = locked.clone();
+                    async move {
+                        lock_books(&tx).await;
+                        locked.notify_one();
+                        std::future::pending::<()>().await;
+                    }
+                })
+                .await
+        }
+    });
+
+    locked.notified().await;
+    transaction.abort();
+    assert!(transaction.await.unwrap_err().is_cancelled());
+
+    wait_for_rollback(&document, &database).await;
+}
+
+#[tokio::test]
+async fn test_database_layers() {
+    let server = TestServer::start().await.unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let counter = requests.clone();
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
+        .layer(tower::layer::layer_fn(move |inner: BoxService| {
+            let counter = counter.clone();
+            inner.map_request(move |request| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                request
+            })
+        }))
+        .connect()
+        .await
+        .unwrap();
+
+    let collection = CollectionRef::new("books");
+    collection
+        .add_document(&test_document())
+        .run(&database)
+        .await
+        .unwrap();
+    collection
+        .list_documents::<DocumentValues>()
+        .fetch_all(&database)
+        .await
+        .unwrap();
+
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[derive(Clone, Default)]
+struct Metrics {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl MetricsRecorder for Metrics {
+    fn rpc_completed(&self, method: &'static str, code: &'static str, _latency: Duration) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", method, code));
+    }
+
+    fn documents_read(&self, collection: &str, count: u64) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("read {} from {}", count, collection));
+    }
+
+    fn documents_written(&self, collection: &str, count: u64) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("wrote {} to {}", count, collection));
+    }
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let server = TestServer::start().await.unwrap();
+    let metrics = Metrics::default();
+
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
+        .metrics(metrics.clone())
+        .connect()
+        .await
+        .unwrap();
+
+    let collection = CollectionRef::new("books");
+    collection
+        .add_document(&test_document())
+        .run(&database)
+        .await
+        .unwrap();
+
+    database
+        .transaction()
+        .run(|tx: ReadPhaseExecutor| async move {
+            let collection = CollectionRef::new("books");
+            collection
+                .list_documents::<DocumentValues>()
+                .fetch_all(&tx)
+                .await
+                .unwrap();
 
             let tx = tx.finish_reads();
+            collection
+                .add_document(&test_document())
+                .run_in(&tx)
+                .await
+                .unwrap();
+        })
+        .await
+        .unwrap();
 
-            let document = DocumentValues::from_hashmap(maplit::hashmap! {
-                "Test".to_string() => Value::Boolean(true)
-            });
+    assert_eq!(
+        *metrics.events.lock().unwrap(),
+        vec![
+            "CreateDocument OK",
+            "wrote 1 to books",
+            "BeginTransaction OK",
+            "ListDocuments OK",
+            "read 1 from books",
+            "Commit OK",
+            "wrote 1 to books",
+        ]
+    );
+}
+
+fn book(title: &str) -> DocumentValues {
+    DocumentValues::from_hashmap(maplit::hashmap! {
+        "title".to_string() => Value::String(title.to_string())
+    })
+}
+
+async fn titles(collection: &CollectionRef, database: &Database) -> Vec<Value> {
+    let mut titles = collection
+        .list_documents::<DocumentValues>()
+        .fetch_all(database)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|document| document.document.into_hashmap()["title"].clone())
+        .collect::<Vec<_>>();
+    titles.sort_by_key(|title| format!("{:?}", title));
+    titles
+}
+
+#[tokio::test]
+async fn test_exporting_and_importing_collections() {
+    let (_server, database) = connect().await;
+    let books = CollectionRef::new("books");
+    books
+        .document("a")
+        .set(&book("Northern Lights"))
+        .run(&database)
+        .await
+        .unwrap();
+    books
+        .document("b")
+        .set(&book("The Subtle Knife"))
+        .run(&database)
+        .await
+        .unwrap();
+    books
+        .document("a")
+        .sub_collection("characters")
+        .document("lyra")
+        .set(&book("Lyra Belacqua"))
+        .run(&database)
+        .await
+        .unwrap();
+
+    let mut export = vec![];
+    let exported = books
+        .export(&mut export)
+        .sub_collections(vec!["characters"])
+        .page_size(1)
+        .run(&database)
+        .await
+        .unwrap();
+    assert_eq!(exported, 3);
+
+    let export = String::from_utf8(export).unwrap();
+    assert_eq!(export.lines().count(), 3);
+    assert!(export.contains(
+        r#"{"fields":{"title":{"stringValue":"Lyra Belacqua"}},"path":"books/a/characters/lyra"}"#
+    ));
+
+    let (_other_server, other_database) = connect().await;
+    let progress = books
+        .import(export.as_bytes())
+        .run(&other_database)
+        .await
+        .unwrap();
+    assert_eq!(
+        progress,
+        ImportProgress {
+            lines: 3,
+            written: 3,
+            skipped: 0
+        }
+    );
+
+    assert_eq!(
+        titles(&books, &other_database).await,
+        titles(&books, &database).await
+    );
+    assert_eq!(
+        titles(
+            &books.document("a").sub_collection("characters"),
+            &other_database
+        )
+        .await,
+        vec![Value::String("Lyra Belacqua".into())]
+    );
+}
+
+#[tokio::test]
+async fn test_import_conflicts() {
+    let (_server, database) = connect().await;
+    let books = CollectionRef::new("books");
+    books
+        .document("a")
+        .set(&book("Old"))
+        .run(&database)
+        .await
+        .unwrap();
+
+    let import = concat!(
+        r#"{"fields":{"title":{"stringValue":"New"}},"path":"books/a"}"#,
+        "\n",
+        r#"{"fields":{"title":{"stringValue":"The Subtle Knife"}},"path":"books/b"}"#,
+        "\n",
+    );
 
-            println!("Adding Document");
-            collection.add_document(&document).run_in(&tx).await;
-            println!("Added Document");
+    let error = books
+        .import(import.as_bytes())
+        .conflicts(ConflictPolicy::Fail)
+        .run(&database)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, ImportError::AlreadyExists { line: 1, path } if path == "books/a"),
+        "{:?}",
+        error
+    );
+
+    let progress = books
+        .import(import.as_bytes())
+        .conflicts(ConflictPolicy::Skip)
+        .run(&database)
+        .await
+        .unwrap();
+    assert_eq!((progress.written, progress.skipped), (1, 1));
+    assert_eq!(
+        titles(&books, &database).await,
+        vec![
+            Value::String("Old".into()),
+            Value::String("The Subtle Knife".into())
+        ]
+    );
+
+    let progress = books
+        .import(import.as_bytes())
+        .conflicts(ConflictPolicy::Overwrite)
+        .run(&database)
+        .await
+        .unwrap();
+    assert_eq!((progress.written, progress.skipped), (2, 0));
+    assert_eq!(
+        titles(&books, &database).await,
+        vec![
+            Value::String("New".into()),
+            Value::String("The Subtle Knife".into())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_resuming_imports() {
+    let (_server, database) = connect().await;
+    let books = CollectionRef::new("books");
+    let import = concat!(
+        r#"{"fields":{"title":{"stringValue":"Northern Lights"}},"path":"books/a"}"#,
+        "\n\n",
+        r#"{"fields":{"title":{"stringValue":"The Subtle Knife"}},"path":"books/b"}"#,
+        "\n",
+        r#"{"fields":{"title":{"stringValue":"Lyra"}},"path":"characters/lyra"}"#,
+        "\n",
+    );
+
+    let reported = Arc::new(Mutex::new(vec![]));
+    let error = books
+        .import(import.as_bytes())
+        .batch_size(1)
+        .on_progress({
+            let reported = reported.clone();
+            move |progress| reported.lock().unwrap().push(progress.lines)
         })
+        .run(&database)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, ImportError::OutsideCollection { line: 4, path } if path == "characters/lyra"),
+        "{:?}",
+        error
+    );
+    assert_eq!(*reported.lock().unwrap(), vec![1, 3]);
+
+    assert_eq!(titles(&books, &database).await.len(), 2);
+
+    let characters = CollectionRef::new("characters");
+    let progress = characters
+        .import(import.as_bytes())
+        .skip_lines(3)
+        .run(&database)
         .await
         .unwrap();
+    assert_eq!(
+        progress,
+        ImportProgress {
+            lines: 4,
+            written: 1,
+            skipped: 0
+        }
+    );
+    assert_eq!(
+        titles(&characters, &database).await,
+        vec![Value::String("Lyra".into())]
+    );
+}
+
+#[tokio::test]
+async fn test_resuming_imports_after_conflicts() {
+    let (_server, database) = connect().await;
+    let books = CollectionRef::new("books");
+    books
+        .document("b")
+        .set(&book("Old"))
+        .run(&database)
+        .await
+        .unwrap();
+
+    let import = concat!(
+        r#"{"fields":{"title":{"stringValue":"Northern Lights"}},"path":"books/a"}"#,
+        "\n",
+        r#"{"fields":{"title":{"stringValue":"The Subtle Knife"}},"path":"books/b"}"#,
+        "\n",
+        r#"{"fields":{"title":{"stringValue":"The Amber Spyglass"}},"path":"books/c"}"#,
+        "\n",
+    );
 
-    println!("Done transaction");
+    let reported = Arc::new(Mutex::new(None));
+    let error = books
+        .import(import.as_bytes())
+        .on_progress({
+            let reported = reported.clone();
+            move |progress| *reported.lock().unwrap() = Some(*progress)
+        })
+        .run(&database)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, ImportError::AlreadyExists { line: 2, path } if path == "books/b"),
+        "{:?}",
+        error
+    );
+
+    // Nothing after the conflict was written, even though it was in the same batch
+    let progress = reported.lock().unwrap().unwrap();
+    assert_eq!(
+        progress,
+        ImportProgress {
+            lines: 1,
+            written: 1,
+            skipped: 0
+        }
+    );
+
+    let progress = books
+        .import(import.as_bytes())
+        .skip_lines(progress.lines + 1)
+        .run(&database)
+        .await
+        .unwrap();
+    assert_eq!(
+        progress,
+        ImportProgress {
+            lines: 3,
+            written: 1,
+            skipped: 0
+        }
+    );
+    assert_eq!(
+        titles(&books, &database).await,
+        vec![
+            Value::String("Northern Lights".into()),
+            Value::String("Old".into()),
+            Value::String("The Amber Spyglass".into())
+        ]
+    );
 }
@@ -0,0 +1,91 @@
+#![cfg(feature = "metrics")]
+
+use ingle::{
+    transactions::ReadPhaseExecutor,
+    values::{DocumentValues, Value},
+    CollectionRef, DatabaseBuilder, MetricsFacade,
+};
+use ingle_testserver::TestServer;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+fn book(title: &str) -> DocumentValues {
+    DocumentValues::from_hashmap(maplit::hashmap! {
+        "title".to_string() => Value::String(title.to_string())
+    })
+}
+
+#[tokio::test]
+async fn test_metrics_facade() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    recorder.install().unwrap();
+
+    let server = TestServer::start().await.unwrap();
+    let database = DatabaseBuilder::new("test-project")
+        .http_endpoint(&server.host())
+        .emulator_owner_credentials()
+        .metrics(MetricsFacade)
+        .connect()
+        .await
+        .unwrap();
+
+    let books = CollectionRef::new("books");
+    books
+        .add_document(&book("Dune"))
+        .run(&database)
+        .await
+        .unwrap();
+
+    database
+        .transaction()
+        .run(|tx: ReadPhaseExecutor| async move {
+            let books = CollectionRef::new("books");
+            books
+                .list_documents::<DocumentValues>()
+                .fetch_all(&tx)
+                .await
+                .unwrap();
+
+            let tx = tx.finish_reads();
+            books.add_document(&book("Emma")).run_in(&tx).await.unwrap();
+        })
+        .await
+        .unwrap();
+
+    let mut recorded = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let (kind, key) = key.into_parts();
+            let labels = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect::<Vec<_>>()
+                .join(",");
+            let value = match value {
+                DebugValue::Counter(count) => count.to_string(),
+                DebugValue::Histogram(values) => format!("{} samples", values.len()),
+                DebugValue::Gauge(value) => value.to_string(),
+            };
+            format!("{:?} {}{{{}}} {}", kind, key.name(), labels, value)
+        })
+        .collect::<Vec<_>>();
+    recorded.sort();
+
+    assert_eq!(
+        recorded,
+        vec![
+            "Counter ingle_documents_read_total{collection=books} 1",
+            "Counter ingle_documents_written_total{collection=books} 2",
+            "Counter ingle_rpcs_total{method=BeginTransaction,code=OK} 1",
+            "Counter ingle_rpcs_total{method=Commit,code=OK} 1",
+            "Counter ingle_rpcs_total{method=CreateDocument,code=OK} 1",
+            "Counter ingle_rpcs_total{method=ListDocuments,code=OK} 1",
+            "Histogram ingle_rpc_duration_seconds{method=BeginTransaction,code=OK} 1 samples",
+            "Histogram ingle_rpc_duration_seconds{method=Commit,code=OK} 1 samples",
+            "Histogram ingle_rpc_duration_seconds{method=CreateDocument,code=OK} 1 samples",
+            "Histogram ingle_rpc_duration_seconds{method=ListDocuments,code=OK} 1 samples",
+        ]
+    );
+}
@@ -56,26 +56,42 @@ fn main() {
 
     match flags.subcommand {
         flags::AppCmd::TonicBuild(_) => {
-            let mut prost_config = prost_build::Config::new();
-            prost_config.disable_comments(["HttpRule"]);
+            // The client for ingle itself
+            compile_protos(
+                tonic_build::configure()
+                    .build_server(false)
+                    .out_dir("ingle/src/google"),
+            );
 
-            tonic_build::configure()
-                .build_server(false)
-                .out_dir("ingle/src/google")
-                .compile_with_config(
-                    prost_config,
-                    &[
-                        "proto/googleapis/google/firestore/v1/common.proto",
-                        "proto/googleapis/google/firestore/v1/document.proto",
-                        "proto/googleapis/google/firestore/v1/firestore.proto",
-                        "proto/googleapis/google/firestore/v1/query.proto",
-                        "proto/googleapis/google/firestore/v1/write.proto",
-                    ],
-                    &["proto/googleapis"],
-                )
-                .unwrap();
+            // The server for ingle-testserver
+            compile_protos(
+                tonic_build::configure()
+                    .build_client(false)
+                    .build_server(true)
+                    .out_dir("ingle-testserver/src/google"),
+            );
         }
     }
 
     println!("Done!");
 }
+
+fn compile_protos(builder: tonic_build::Builder) {
+    let mut prost_config = prost_build::Config::new();
+    prost_config.disable_comments(["HttpRule", "ErrorInfo"]);
+
+    builder
+        .compile_with_config(
+            prost_config,
+            &[
+                "proto/googleapis/google/firestore/v1/common.proto",
+                "proto/googleapis/google/firestore/v1/document.proto",
+                "proto/googleapis/google/firestore/v1/firestore.proto",
+                "proto/googleapis/google/firestore/v1/query.proto",
+                "proto/googleapis/google/firestore/v1/write.proto",
+                "proto/googleapis/google/rpc/error_details.proto",
+            ],
+            &["proto/googleapis"],
+        )
+        .unwrap();
+}